@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
@@ -14,104 +15,346 @@ fn get_input(filename: &str) -> Vec<String> {
     lines
 }
 
-#[derive(Debug)]
-struct Range {
-    start: usize,
-    end: usize,
-    start_str: String,
-    end_str: String,
+// Running count and sum of the invalid IDs found in a range. The sum is kept
+// as a u128 since a handful of 19 digit IDs is already enough to overflow a
+// usize.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Totals {
+    count: u128,
+    sum: u128,
 }
 
-impl Range {
-    pub fn check_range(&self) -> usize {
-        let mut sum = 0;
-        let s_midpoint = self.start_str.len() / 2;
-        let (sl, _) = self.start_str.split_at(s_midpoint);
+impl Totals {
+    fn add(&self, other: &Totals) -> Totals {
+        Totals {
+            count: self.count + other.count,
+            sum: self.sum + other.sum,
+        }
+    }
 
-        let mut start_left = sl.parse::<usize>().unwrap_or_default();
+    fn sub(&self, other: &Totals) -> Totals {
+        Totals {
+            count: self.count - other.count,
+            sum: self.sum - other.sum,
+        }
+    }
+}
+
+fn num_digits(mut value: u128, radix: u32) -> u32 {
+    let radix = radix as u128;
+    let mut digits = 1;
+    while value >= radix {
+        value /= radix;
+        digits += 1;
+    }
+    digits
+}
 
-        // We can't start with zero, so just try moving things along first
-        if start_left == 0 {
-            start_left = 1;
+// Every ID made of `copies` back-to-back copies of a `block_len` digit block
+// is just that block times a fixed multiplier, e.g. 1001 for two copies of a
+// 3 digit block, or 10101 for three copies of a 2 digit block in base 10.
+fn repeat_multiplier(radix: u32, block_len: u32, copies: u32) -> u128 {
+    (0..copies)
+        .map(|i| (radix as u128).pow(i * block_len))
+        .sum()
+}
+
+fn to_radix_string(mut value: usize, radix: u32) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((value % radix as usize) as u32, radix).unwrap());
+        value /= radix as usize;
+        if value == 0 {
+            break;
         }
+    }
+    digits.iter().rev().collect()
+}
 
-        loop {
-            let sl = format!("{start_left}");
-            let try_str = [sl.clone(), sl.clone()].concat();
-            let try_val = try_str.parse::<usize>().unwrap();
-            if try_val >= self.start && try_val <= self.end {
-                //println!("found: {try_val}");
-                sum += try_val;
-            }
-            if try_val >= self.end {
-                break;
-            }
-            start_left += 1;
+// All the divisors of `value`, in increasing order.
+fn divisors(value: u32) -> Vec<u32> {
+    (1..=value).filter(|d| value.is_multiple_of(*d)).collect()
+}
+
+// Which repeated IDs count as invalid. The number of copies an ID splits
+// into depends on the block length chosen, so 1111 is two copies of 11 as
+// well as four copies of 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum RepeatPolicy {
+    // Splits into exactly k equal blocks (part 1 is Exactly(2)).
+    Exactly(u32),
+    // Splits into k or more equal blocks (part 2 is AtLeast(2)).
+    AtLeast(u32),
+    // Splits into any one of the listed numbers of equal blocks.
+    AnyOf(Vec<u32>),
+    // The shortest repeating block appears exactly k times, so 1111 only
+    // counts as four copies.
+    Primitive(u32),
+}
+
+impl RepeatPolicy {
+    // Whether a `digits` long ID whose shortest repeating block is
+    // `block_len` digits long is invalid. An ID that is c copies of its
+    // shortest block splits into k equal blocks exactly when k divides c.
+    fn accepts(&self, digits: u32, block_len: u32) -> bool {
+        let copies = digits / block_len;
+        match self {
+            RepeatPolicy::Exactly(k) => *k > 0 && copies.is_multiple_of(*k),
+            RepeatPolicy::AtLeast(k) => copies >= *k,
+            RepeatPolicy::AnyOf(ks) => ks.iter().any(|k| *k > 0 && copies.is_multiple_of(*k)),
+            RepeatPolicy::Primitive(k) => copies == *k,
         }
+    }
+}
 
-        sum
+// Length of the shortest block that repeats to make up `id`.
+fn primitive_block_len(id: u128, digits: u32, radix: u32) -> u32 {
+    for block_len in divisors(digits) {
+        let block = id / (radix as u128).pow(digits - block_len);
+        if block * repeat_multiplier(radix, block_len, digits / block_len) == id {
+            return block_len;
+        }
     }
+    digits
+}
+
+// A single invalid ID, along with the shortest block that repeats to make it
+// up and how many copies of that block there are. Ordering is by ID first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct InvalidId {
+    id: usize,
+    block: usize,
+    copies: u32,
+}
 
-    fn check_range_digits(&self, repeating_digits: usize) -> HashSet<usize> {
-        let starting_value = 10usize.pow(repeating_digits as u32 - 1);
+// One arithmetic series of IDs with the same number of digits and block
+// length, ordered so the heap always hands back the lowest pending ID first,
+// and the shortest block length when several series share that ID.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct RepeatSeries {
+    id: u128,
+    block_len: u32,
+    block: u128,
+    last_block: u128,
+    multiplier: u128,
+}
 
-        let start_len = self.start_str.len();
-        let end_len = self.end_str.len();
-        let mut num_start_copies = start_len / repeating_digits;
-        let num_end_copies = end_len / repeating_digits;
+// Walks the invalid IDs of a range in increasing order without duplicates.
+// Only the series for the current number of digits are kept in the heap,
+// since every shorter ID sorts before every longer one.
+#[derive(Debug)]
+struct InvalidIds {
+    policy: RepeatPolicy,
+    radix: u32,
+    start: u128,
+    end: u128,
+    digits: u32,
+    last_digits: u32,
+    series: BinaryHeap<Reverse<RepeatSeries>>,
+    last_id: Option<u128>,
+}
 
-        // Make sure we don't try to use just a single copy of something
-        if num_start_copies == 1 {
-            num_start_copies = 2;
+impl InvalidIds {
+    fn new(policy: &RepeatPolicy, radix: u32, start: usize, end: usize) -> InvalidIds {
+        let (start, end) = (start as u128, end as u128);
+        InvalidIds {
+            policy: policy.clone(),
+            radix,
+            start,
+            end,
+            digits: num_digits(start, radix),
+            last_digits: if start > end {
+                0
+            } else {
+                num_digits(end, radix)
+            },
+            series: BinaryHeap::new(),
+            last_id: None,
         }
+    }
 
-        let mut invalid_ids = HashSet::new();
+    // Queue up every series of `digits` long IDs that fall inside the range
+    // and whose block length the policy allows.
+    fn seed(&mut self, digits: u32) {
+        for block_len in divisors(digits) {
+            if !self.policy.accepts(digits, block_len) {
+                continue;
+            }
+            let radix = self.radix as u128;
+            let multiplier = repeat_multiplier(self.radix, block_len, digits / block_len);
+            let block = radix
+                .pow(block_len - 1)
+                .max(self.start.div_ceil(multiplier));
+            let last_block = (radix.pow(block_len) - 1).min(self.end / multiplier);
+            if block <= last_block {
+                self.series.push(Reverse(RepeatSeries {
+                    id: block * multiplier,
+                    block_len,
+                    block,
+                    last_block,
+                    multiplier,
+                }));
+            }
+        }
+    }
+}
 
-        // We need to handle the case where the number of digits changes
-        // between the start and end values, so we just iterate over
-        // how many copies would be needed for the start value through
-        // the number of copies needed for the end value.
-        for copies in num_start_copies..=num_end_copies {
-            let mut value = starting_value;
+impl Iterator for InvalidIds {
+    type Item = InvalidId;
 
-            // Since we're using a loop over "copies", we can just stop once
-            // we have rolled over into a different number of digits in the
-            // string to copy. The higher range will be handled in a different
-            // instance of this function/loop.
-            while value < 10 * starting_value {
-                let mut copy_value = 0;
-                for i in 0..copies {
-                    copy_value += value * 10u32.pow(i as u32 * repeating_digits as u32) as usize;
+    fn next(&mut self) -> Option<InvalidId> {
+        loop {
+            let Some(Reverse(mut series)) = self.series.pop() else {
+                if self.digits > self.last_digits {
+                    return None;
                 }
+                self.seed(self.digits);
+                self.digits += 1;
+                continue;
+            };
+
+            let id = series.id;
+            if series.block < series.last_block {
+                series.block += 1;
+                series.id += series.multiplier;
+                self.series.push(Reverse(series));
+            }
 
-                if copy_value > self.end {
-                    break;
-                }
+            // The same ID shows up in the series for each block length it
+            // repeats, so only look at it the first time around.
+            if self.last_id == Some(id) {
+                continue;
+            }
+            self.last_id = Some(id);
+
+            // Series for longer blocks also contain IDs with a shorter
+            // repeating block, which the policy might not allow.
+            let digits = num_digits(id, self.radix);
+            let block_len = primitive_block_len(id, digits, self.radix);
+            if !self.policy.accepts(digits, block_len) {
+                continue;
+            }
 
-                if copy_value >= self.start {
-                    //println!("Found: {copy_value}");
-                    invalid_ids.insert(copy_value);
-                }
+            return Some(InvalidId {
+                id: id as usize,
+                block: (id / (self.radix as u128).pow(digits - block_len)) as usize,
+                copies: digits / block_len,
+            });
+        }
+    }
+}
 
-                value += 1;
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Range {
+    start: usize,
+    end: usize,
+    radix: u32,
+}
+
+impl Range {
+    // Count and sum every `digits` long ID in the range that is made up of
+    // copies of a `block_len` digit block. The valid blocks form a
+    // contiguous span, so the IDs are an arithmetic series with a step of
+    // the repeat multiplier.
+    fn repeated_totals(&self, digits: u32, block_len: u32) -> Totals {
+        let radix = self.radix as u128;
+        let multiplier = repeat_multiplier(self.radix, block_len, digits / block_len);
+        let min_block = radix.pow(block_len - 1);
+        let max_block = radix.pow(block_len) - 1;
+
+        let lo = min_block.max((self.start as u128).div_ceil(multiplier));
+        let hi = max_block.min(self.end as u128 / multiplier);
+        if lo > hi {
+            return Totals::default();
+        }
+
+        let count = hi - lo + 1;
+        // One of count or (lo + hi) is always even, so halve that one first
+        // to keep the intermediate value small.
+        let block_sum = if count.is_multiple_of(2) {
+            (count / 2) * (lo + hi)
+        } else {
+            count * ((lo + hi) / 2)
+        };
+
+        Totals {
+            count,
+            sum: block_sum * multiplier,
+        }
+    }
+
+    // Totals for the `digits` long IDs in the range, split up by the length
+    // of their shortest repeating block. An ID that repeats a block of
+    // length p also repeats every block length that p divides (and that
+    // divides `digits`), so we peel those off with inclusion-exclusion
+    // working upwards from the shortest blocks. The result lines up with
+    // `divisors(digits)`.
+    fn primitive_totals(&self, digits: u32) -> Vec<Totals> {
+        let block_lens = divisors(digits);
+        let mut primitive: Vec<Totals> = Vec::new();
+        for (i, &block_len) in block_lens.iter().enumerate() {
+            let mut totals = self.repeated_totals(digits, block_len);
+            for j in 0..i {
+                if block_len % block_lens[j] == 0 {
+                    totals = totals.sub(&primitive[j]);
+                }
             }
+            primitive.push(totals);
         }
+        primitive
+    }
 
-        invalid_ids
+    fn digit_counts(&self) -> std::ops::RangeInclusive<u32> {
+        num_digits(self.start as u128, self.radix)..=num_digits(self.end as u128, self.radix)
     }
 
-    pub fn check_range_part2(&self) -> usize {
-        let mut invalid_ids = HashSet::new();
-        let mut sum = 0;
-        for i in 1..=self.end_str.len() / 2 {
-            invalid_ids.extend(self.check_range_digits(i));
+    pub fn totals(&self, policy: &RepeatPolicy) -> Totals {
+        let mut totals = Totals::default();
+        for digits in self.digit_counts() {
+            let block_lens = divisors(digits);
+            let primitive = self.primitive_totals(digits);
+            for (block_len, t) in block_lens.iter().zip(primitive.iter()) {
+                if policy.accepts(digits, *block_len) {
+                    totals = totals.add(t);
+                }
+            }
         }
-        invalid_ids.iter().for_each(|id| { sum += id; });
+        totals
+    }
 
-        sum
+    // Every invalid ID in the range, in increasing order.
+    pub fn invalid_ids(&self, policy: &RepeatPolicy) -> InvalidIds {
+        self.invalid_ids_between(policy, self.start, self.end)
+    }
+
+    // The invalid IDs that also fall within lo..=hi.
+    pub fn invalid_ids_between(&self, policy: &RepeatPolicy, lo: usize, hi: usize) -> InvalidIds {
+        InvalidIds::new(policy, self.radix, self.start.max(lo), self.end.min(hi))
     }
 }
 
+// Parse a single ID along with the base it was written in. Plain numbers are
+// decimal, 0x/0o/0b pick hex, octal and binary, and any other base from 2 to
+// 36 can be spelled out as e.g. 36#ZZ.
+fn parse_id(s: &str) -> (usize, u32) {
+    let (radix, digits) = if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        (16, hex)
+    } else if let Some(oct) = s.strip_prefix("0o").or(s.strip_prefix("0O")) {
+        (8, oct)
+    } else if let Some(bin) = s.strip_prefix("0b").or(s.strip_prefix("0B")) {
+        (2, bin)
+    } else if let Some((radix, digits)) = s.split_once('#') {
+        (radix.parse().unwrap(), digits)
+    } else {
+        (10, s)
+    };
+
+    if !(2..=36).contains(&radix) {
+        panic!("Invalid base: {s}");
+    }
+    (usize::from_str_radix(digits, radix).unwrap(), radix)
+}
+
 fn parse_lines(lines: &Vec<String>) -> Vec<Range> {
     let mut v: Vec<Range> = Vec::new();
     for line in lines {
@@ -121,68 +364,408 @@ fn parse_lines(lines: &Vec<String>) -> Vec<Range> {
             if rs.len() != 2 {
                 panic!("Invalid range: {line}");
             }
+            let (start, start_radix) = parse_id(rs[0]);
+            let (end, end_radix) = parse_id(rs[1]);
+            if start_radix != end_radix {
+                panic!("Mismatched bases: {r}");
+            }
             v.push(Range {
-                start: rs[0].parse().unwrap(),
-                end: rs[1].parse().unwrap(),
-                start_str: rs[0].to_string(),
-                end_str: rs[1].to_string(),
+                start,
+                end,
+                radix: start_radix,
             });
         }
     }
     v
 }
 
-fn compute_part1(ranges: &Vec<Range>) -> usize {
+fn compute_invalid(ranges: &Vec<Range>, policy: &RepeatPolicy) -> u128 {
     let mut sum = 0;
 
     for r in ranges {
-        sum += r.check_range();
+        sum += r.totals(policy).sum;
     }
 
-    println!("invalid ids: {sum}");
+    println!("invalid ids ({policy:?}): {sum}");
 
     sum
 }
 
-fn compute_part2(ranges: &Vec<Range>) -> usize {
-    let mut sum = 0;
+// The input ranges sorted and with any overlapping ranges (in the same base)
+// merged together. Each overlap is reported as a pair of indices into the
+// original list: the range that overlapped, and the earlier range (in sorted
+// order) it ran into.
+#[derive(Debug)]
+struct NormalizedRanges {
+    ranges: Vec<Range>,
+    overlaps: Vec<(usize, usize)>,
+}
+
+fn normalize_ranges(ranges: &[Range]) -> NormalizedRanges {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|&i| (ranges[i].radix, ranges[i].start, ranges[i].end));
+
+    let mut merged: Vec<Range> = Vec::new();
+    let mut overlaps = Vec::new();
+    // Index of the input range reaching furthest into the current merged one.
+    let mut furthest = 0;
+    for i in order {
+        let r = &ranges[i];
+        if let Some(last) = merged.last_mut() {
+            if last.radix == r.radix && r.start <= last.end {
+                overlaps.push((i, furthest));
+                if r.end > last.end {
+                    last.end = r.end;
+                    furthest = i;
+                }
+                continue;
+            }
+        }
+        merged.push(r.clone());
+        furthest = i;
+    }
+
+    NormalizedRanges {
+        ranges: merged,
+        overlaps,
+    }
+}
+
+// What each input range contributes on its own, next to the totals with every
+// invalid ID only counted once no matter how many ranges contain it.
+#[derive(Debug)]
+struct InvalidReport {
+    per_range: Vec<Totals>,
+    combined: Totals,
+    unique: Totals,
+}
+
+fn report_invalid(ranges: &[Range], policy: &RepeatPolicy) -> InvalidReport {
+    let per_range: Vec<Totals> = ranges.iter().map(|r| r.totals(policy)).collect();
+    let combined = per_range
+        .iter()
+        .fold(Totals::default(), |acc, t| acc.add(t));
+    let unique = normalize_ranges(ranges)
+        .ranges
+        .iter()
+        .fold(Totals::default(), |acc, r| acc.add(&r.totals(policy)));
+
+    InvalidReport {
+        per_range,
+        combined,
+        unique,
+    }
+}
+
+fn print_report(ranges: &[Range], report: &InvalidReport) {
+    for (r, t) in ranges.iter().zip(report.per_range.iter()) {
+        println!(
+            "{}-{}: {} invalid, sum {}",
+            to_radix_string(r.start, r.radix),
+            to_radix_string(r.end, r.radix),
+            t.count,
+            t.sum
+        );
+    }
+    println!(
+        "combined: {} invalid, sum {}",
+        report.combined.count, report.combined.sum
+    );
+    println!(
+        "unique: {} invalid, sum {}",
+        report.unique.count, report.unique.sum
+    );
+}
+
+// Like compute_invalid, but merges overlapping ranges first so each invalid
+// ID only counts once.
+fn compute_unique_invalid(ranges: &[Range], policy: &RepeatPolicy) -> u128 {
+    let normalized = normalize_ranges(ranges);
+    for (i, j) in &normalized.overlaps {
+        println!(
+            "range {i} ({:?}) overlaps range {j} ({:?})",
+            ranges[*i], ranges[*j]
+        );
+    }
+
+    compute_invalid(&normalized.ranges, policy)
+}
+
+fn compute_part1(ranges: &Vec<Range>) -> u128 {
+    compute_invalid(ranges, &RepeatPolicy::Exactly(2))
+}
+
+fn compute_part2(ranges: &Vec<Range>) -> u128 {
+    compute_invalid(ranges, &RepeatPolicy::AtLeast(2))
+}
+
+fn list_invalid_ids(ranges: &Vec<Range>, policy: &RepeatPolicy) -> usize {
+    let mut count = 0;
 
     for r in ranges {
-        sum += r.check_range_part2();
+        for invalid in r.invalid_ids(policy) {
+            println!(
+                "{} = {} x {}",
+                to_radix_string(invalid.id, r.radix),
+                to_radix_string(invalid.block, r.radix),
+                invalid.copies
+            );
+            count += 1;
+        }
     }
 
-    println!("invalid ids part 2: {sum}");
+    println!("invalid id count: {count}");
 
-    sum
+    count
 }
 
 #[test]
 fn test_prelim() {
-    let invalid_ids = compute_part1(&mut parse_lines(&get_input("prelim.txt")));
+    let invalid_ids = compute_part1(&parse_lines(&get_input("prelim.txt")));
     assert_eq!(invalid_ids, 1227775554);
 }
 
 #[test]
 fn test_part1() {
-    let invalid_ids = compute_part1(&mut parse_lines(&get_input("input.txt")));
+    let invalid_ids = compute_part1(&parse_lines(&get_input("input.txt")));
     assert_eq!(invalid_ids, 34826702005);
 }
 
 #[test]
 fn test_prelim2() {
-    let invalid_ids = compute_part2(&mut parse_lines(&get_input("prelim.txt")));
+    let invalid_ids = compute_part2(&parse_lines(&get_input("prelim.txt")));
     assert_eq!(invalid_ids, 4174379265);
 }
 
 #[test]
 fn test_part2() {
-    let invalid_ids = compute_part2(&mut parse_lines(&get_input("input.txt")));
+    let invalid_ids = compute_part2(&parse_lines(&get_input("input.txt")));
     assert_eq!(invalid_ids, 43287141963);
 }
 
+#[test]
+fn test_matches_brute_force() {
+    let range = Range {
+        start: 1,
+        end: 250_000,
+        radix: 10,
+    };
+    let mut part1 = 0;
+    let mut part2 = 0;
+    for id in range.start..=range.end {
+        let s = format!("{id}");
+        let len = s.len();
+        if len % 2 == 0 && s == s[..len / 2].repeat(2) {
+            part1 += id as u128;
+        }
+        if (2..=len).any(|copies| len % copies == 0 && s == s[..len / copies].repeat(copies)) {
+            part2 += id as u128;
+        }
+    }
+    assert_eq!(range.totals(&RepeatPolicy::Exactly(2)).sum, part1);
+    assert_eq!(range.totals(&RepeatPolicy::AtLeast(2)).sum, part2);
+}
+
+#[test]
+fn test_huge_range() {
+    // Every 19 and 20 digit ID, which would take forever to walk one by one.
+    let range = Range {
+        start: 1_000_000_000_000_000_000,
+        end: usize::MAX,
+        radix: 10,
+    };
+    let totals = range.totals(&RepeatPolicy::Exactly(2));
+    // 20 digit IDs that are two copies of a 10 digit block, up to usize::MAX
+    // = 18446744073709551615, so blocks 1000000000..=1844674407.
+    assert_eq!(totals.count, 1844674407 - 1000000000 + 1);
+    let totals = range.totals(&RepeatPolicy::AtLeast(2));
+    assert!(totals.count > range.totals(&RepeatPolicy::Exactly(2)).count);
+}
+
+#[test]
+fn test_invalid_ids() {
+    let part2 = RepeatPolicy::AtLeast(2);
+    let ranges = parse_lines(&get_input("prelim.txt"));
+    let mut all = Vec::new();
+    for r in &ranges {
+        let ids: Vec<_> = r.invalid_ids(&part2).collect();
+        assert!(ids.windows(2).all(|w| w[0].id < w[1].id));
+        assert_eq!(ids.len() as u128, r.totals(&part2).count);
+        all.extend(ids);
+    }
+    let sum: u128 = all.iter().map(|i| i.id as u128).sum();
+    assert_eq!(sum, 4174379265);
+    assert_eq!(list_invalid_ids(&ranges, &part2), all.len());
+
+    let r = &ranges[1];
+    let ids: Vec<_> = r.invalid_ids(&part2).collect();
+    assert_eq!(
+        ids,
+        vec![
+            InvalidId {
+                id: 99,
+                block: 9,
+                copies: 2
+            },
+            InvalidId {
+                id: 111,
+                block: 1,
+                copies: 3
+            },
+        ]
+    );
+    assert_eq!(r.invalid_ids(&part2).min().map(|i| i.id), Some(99));
+    assert_eq!(r.invalid_ids(&part2).max().map(|i| i.id), Some(111));
+    assert_eq!(r.invalid_ids_between(&part2, 100, 200).count(), 1);
+
+    let r = Range {
+        start: 2121212118,
+        end: 2121212124,
+        radix: 10,
+    };
+    assert_eq!(
+        r.invalid_ids(&part2).collect::<Vec<_>>(),
+        vec![InvalidId {
+            id: 2121212121,
+            block: 21,
+            copies: 5
+        }]
+    );
+}
+
+#[test]
+fn test_repeat_policies() {
+    let range = Range {
+        start: 1,
+        end: 200_000,
+        radix: 10,
+    };
+    let policies = [
+        RepeatPolicy::Exactly(3),
+        RepeatPolicy::AtLeast(3),
+        RepeatPolicy::AnyOf(vec![2, 5]),
+        RepeatPolicy::Primitive(2),
+        RepeatPolicy::Primitive(6),
+    ];
+    for policy in &policies {
+        let mut expected = Totals::default();
+        for id in range.start..=range.end {
+            let s = format!("{id}");
+            let len = s.len();
+            let splits = |k: usize| len.is_multiple_of(k) && s == s[..len / k].repeat(k);
+            let primitive = (1..=len).rev().find(|k| splits(*k)).unwrap();
+            let invalid = match policy {
+                RepeatPolicy::Exactly(k) => splits(*k as usize),
+                RepeatPolicy::AtLeast(k) => (*k as usize..=len).any(splits),
+                RepeatPolicy::AnyOf(ks) => ks.iter().any(|k| splits(*k as usize)),
+                RepeatPolicy::Primitive(k) => primitive == *k as usize,
+            };
+            if invalid {
+                expected = expected.add(&Totals {
+                    count: 1,
+                    sum: id as u128,
+                });
+            }
+        }
+        assert_eq!(range.totals(policy), expected, "{policy:?}");
+
+        let ids: Vec<_> = range.invalid_ids(policy).collect();
+        assert_eq!(ids.len() as u128, expected.count, "{policy:?}");
+        assert_eq!(ids.iter().map(|i| i.id as u128).sum::<u128>(), expected.sum);
+    }
+}
+
+#[test]
+fn test_other_bases() {
+    let ranges = parse_lines(&vec!["0x1A-0xFF,0b101-0b111111,36#A-36#ZZ".to_string()]);
+    assert_eq!(
+        (ranges[0].start, ranges[0].end, ranges[0].radix),
+        (0x1a, 0xff, 16)
+    );
+    assert_eq!(
+        (ranges[1].start, ranges[1].end, ranges[1].radix),
+        (5, 63, 2)
+    );
+    assert_eq!(
+        (ranges[2].start, ranges[2].end, ranges[2].radix),
+        (10, 36 * 36 - 1, 36)
+    );
+
+    // 0x1A..=0xFF holds 0x22, 0x33, ... 0xFF.
+    let hex = ranges[0].totals(&RepeatPolicy::AtLeast(2));
+    assert_eq!(hex.count, 14);
+    assert_eq!(hex.sum, (2..=15).map(|d| d * 0x11).sum::<u128>());
+
+    for radix in [2, 3, 7, 16, 36] {
+        let range = Range {
+            start: 1,
+            end: 20_000,
+            radix,
+        };
+        let mut expected = Totals::default();
+        for id in range.start..=range.end {
+            let s = to_radix_string(id, radix);
+            let len = s.len();
+            if (2..=len).any(|k| len.is_multiple_of(k) && s == s[..len / k].repeat(k)) {
+                expected = expected.add(&Totals {
+                    count: 1,
+                    sum: id as u128,
+                });
+            }
+        }
+        assert_eq!(range.totals(&RepeatPolicy::AtLeast(2)), expected);
+        let listed: u128 = range
+            .invalid_ids(&RepeatPolicy::AtLeast(2))
+            .map(|i| i.id as u128)
+            .sum();
+        assert_eq!(listed, expected.sum);
+    }
+}
+
+#[test]
+fn test_overlapping_ranges() {
+    let ranges = parse_lines(&vec!["95-115,11-22,100-120,12-21,0x10-0x20".to_string()]);
+    let normalized = normalize_ranges(&ranges);
+    let spans: Vec<_> = normalized
+        .ranges
+        .iter()
+        .map(|r| (r.start, r.end, r.radix))
+        .collect();
+    // 0x10-0x20 is 16-32, but in a different base so it stays separate.
+    assert_eq!(spans, vec![(11, 22, 10), (95, 120, 10), (16, 32, 16)]);
+    assert_eq!(normalized.overlaps, vec![(3, 1), (2, 0)]);
+
+    let policy = RepeatPolicy::AtLeast(2);
+    let report = report_invalid(&ranges, &policy);
+    assert_eq!(report.per_range[0].sum, 99 + 111);
+    assert_eq!(report.per_range[2].sum, 111);
+    assert_eq!(report.per_range[3].count, 0);
+    assert_eq!(report.combined.sum, 99 + 111 + 11 + 22 + 111 + 0x11);
+    assert_eq!(report.unique.sum, 11 + 22 + 99 + 111 + 0x11);
+    assert_eq!(compute_unique_invalid(&ranges, &policy), report.unique.sum);
+}
+
 fn main() {
-    compute_part1(&mut parse_lines(&get_input("prelim.txt")));
-    compute_part1(&mut parse_lines(&get_input("input.txt")));
-    compute_part2(&mut parse_lines(&get_input("prelim.txt")));
-    compute_part2(&mut parse_lines(&get_input("input.txt")));
+    compute_part1(&parse_lines(&get_input("prelim.txt")));
+    compute_part1(&parse_lines(&get_input("input.txt")));
+    compute_part2(&parse_lines(&get_input("prelim.txt")));
+    compute_part2(&parse_lines(&get_input("input.txt")));
+    compute_invalid(
+        &parse_lines(&get_input("input.txt")),
+        &RepeatPolicy::Primitive(2),
+    );
+    compute_invalid(
+        &parse_lines(&get_input("input.txt")),
+        &RepeatPolicy::AnyOf(vec![2, 3]),
+    );
+    compute_unique_invalid(
+        &parse_lines(&get_input("input.txt")),
+        &RepeatPolicy::AtLeast(2),
+    );
+    let ranges = parse_lines(&get_input("input.txt"));
+    print_report(&ranges, &report_invalid(&ranges, &RepeatPolicy::AtLeast(2)));
+    list_invalid_ids(
+        &parse_lines(&get_input("prelim.txt")),
+        &RepeatPolicy::AtLeast(2),
+    );
 }
@@ -66,30 +66,497 @@ fn compute_joltage(batteries: &Vec<Vec<u64>>) -> u64 {
     joltage
 }
 
+// How to pick the batteries in a bank. The puzzle itself is the largest
+// number from exactly n batteries, with nothing stopping two neighbours both
+// being turned on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Objective {
+    maximize: bool,
+    min_count: usize,
+    max_count: usize,
+    // Chosen batteries have to be at least this many positions apart, so 1
+    // allows neighbours and 2 leaves a gap between each of them.
+    min_spacing: usize,
+}
+
+impl Objective {
+    fn max(n: usize) -> Objective {
+        Objective {
+            maximize: true,
+            min_count: n,
+            max_count: n,
+            min_spacing: 1,
+        }
+    }
+
+    fn min(n: usize) -> Objective {
+        Objective {
+            maximize: false,
+            ..Objective::max(n)
+        }
+    }
+}
+
+// Indices of the n batteries to turn on to get the largest (or smallest)
+// joltage, which is the same as removing len - n digits from the bank. A
+// digit is popped off the stack whenever a better one comes along while
+// there are still removals to spend, so every index is pushed and popped at
+// most once.
+fn select_greedy(bank: &[u64], n: usize, maximize: bool) -> Vec<usize> {
+    let mut removals = bank.len() - n;
+    let mut stack: Vec<usize> = Vec::with_capacity(bank.len());
+
+    for (idx, &digit) in bank.iter().enumerate() {
+        while removals > 0
+            && stack.last().is_some_and(|&top| {
+                if maximize {
+                    bank[top] < digit
+                } else {
+                    bank[top] > digit
+                }
+            })
+        {
+            stack.pop();
+            removals -= 1;
+        }
+        stack.push(idx);
+    }
+
+    // Anything left over comes off the end, where it matters least.
+    stack.truncate(n);
+    stack
+}
+
+fn select_max(bank: &[u64], n: usize) -> Vec<usize> {
+    select_greedy(bank, n, true)
+}
+
+// The decimal digits of each cell in a bank, so multi-digit cells can be
+// glued together the same way single batteries are.
+fn cell_digits(value: u64) -> Vec<u8> {
+    value.to_string().bytes().map(|b| b - b'0').collect()
+}
+
+// With gaps between the batteries the stack can't be used, but picking
+// digits one at a time still works when every cell is a single digit: take
+// the earliest best digit from just past the last pick up to the last spot
+// that still leaves room for the rest.
+fn select_spaced(bank: &[u64], n: usize, spacing: usize, maximize: bool) -> Option<Vec<usize>> {
+    if n == 0 {
+        return Some(Vec::new());
+    }
+    if (n - 1) * spacing >= bank.len() {
+        return None;
+    }
+
+    let target = if maximize { 9 } else { 0 };
+    let mut indices = Vec::with_capacity(n);
+    let mut start = 0;
+    for remaining in (0..n).rev() {
+        let last = bank.len() - 1 - remaining * spacing;
+        let mut best = start;
+        for idx in start..=last {
+            if bank[best] == target {
+                break;
+            }
+            let better = if maximize {
+                bank[idx] > bank[best]
+            } else {
+                bank[idx] < bank[best]
+            };
+            if better {
+                best = idx;
+            }
+        }
+        indices.push(best);
+        start = best + spacing;
+    }
+    Some(indices)
+}
+
+// Whether a beats b for the objective.
+fn better<T: PartialOrd>(maximize: bool, a: T, b: T) -> bool {
+    if maximize {
+        a > b
+    } else {
+        a < b
+    }
+}
+
+// Marks a DP state that can't be filled.
+const NONE: u32 = u32::MAX;
+
+// The general case, for when the greedy picks no longer work: multi-digit
+// cells, or a choice in how many to turn on that isn't just "as many as
+// possible".
+//
+// Rather than keeping every tail around, this only keeps numbers and
+// back-pointers, so it needs O(len * max_count) memory:
+//
+// - length[i][k] is how many digits the best way to turn on k batteries from
+//   position i onwards has. Behind a non-zero prefix more digits always win
+//   (for maximizing), so this settles most comparisons on its own.
+// - Tails of the same length are compared by rank. Taking cell i and then
+//   the best tail from there is spelled out one digit at a time, and each
+//   digit plus the rank of whatever follows it gives a rank among all digit
+//   strings of that length, shortest first.
+// - first[i][k] is the first cell the best tail takes, which is all it takes
+//   to walk the choices back at the end.
+//
+// Behind nothing but zeros the tail's own leading zeros stop counting, so a
+// second pass works out the best numeric value, skipping over zero cells.
+fn select_dp(bank: &[u64], objective: &Objective) -> Option<Vec<usize>> {
+    let len = bank.len();
+    let step = objective.min_spacing.max(1);
+    let max_count = objective.max_count;
+    let maximize = objective.maximize;
+    let cells: Vec<Vec<u8>> = bank.iter().map(|v| cell_digits(*v)).collect();
+
+    let next = |i: usize| (i + step).min(len);
+    let width = max_count + 1;
+    let state = |i: usize, k: usize| i * width + k;
+
+    let mut length = vec![NONE; (len + 1) * width];
+    for i in (0..=len).rev() {
+        length[state(i, 0)] = 0;
+        if i == len {
+            continue;
+        }
+        for k in 1..=max_count {
+            let skip = length[state(i + 1, k)];
+            let tail = length[state(next(i), k - 1)];
+            let take = if tail == NONE {
+                NONE
+            } else {
+                tail + cells[i].len() as u32
+            };
+            length[state(i, k)] = match (take, skip) {
+                (NONE, s) => s,
+                (t, NONE) => t,
+                (t, s) => {
+                    if better(maximize, s, t) {
+                        s
+                    } else {
+                        t
+                    }
+                }
+            };
+        }
+    }
+
+    // Digit p of cell j, followed by the best way to turn on k - 1 more
+    // batteries after it. Cells are laid end to end, offset[j] digits in.
+    let mut offset = vec![0; len + 1];
+    for j in 0..len {
+        offset[j + 1] = offset[j] + cells[j].len();
+    }
+    let digits = offset[len];
+    let node = |j: usize, k: usize, p: usize| (k - 1) * digits + offset[j] + p;
+    let tail_len = |j: usize, k: usize| length[state(next(j), k - 1)];
+
+    // Sort the digits and the states by length, with the states in each
+    // length from the back of the bank forwards. Taking a cell can make a
+    // tail longer than the best one when minimizing, by up to a cell.
+    let max_len = length
+        .iter()
+        .filter(|l| **l != NONE)
+        .max()
+        .map_or(0, |l| *l) as usize
+        + cells.iter().map(|c| c.len()).max().unwrap_or(0);
+    let mut node_levels: Vec<Vec<(usize, usize, usize)>> = vec![Vec::new(); max_len + 1];
+    let mut state_levels: Vec<Vec<(usize, usize)>> = vec![Vec::new(); max_len + 1];
+    for k in 1..=max_count {
+        for j in (0..len).rev() {
+            if tail_len(j, k) != NONE {
+                for p in 0..cells[j].len() {
+                    let level = cells[j].len() - p + tail_len(j, k) as usize;
+                    node_levels[level].push((j, k, p));
+                }
+            }
+            if length[state(j, k)] != NONE {
+                state_levels[length[state(j, k)] as usize].push((j, k));
+            }
+        }
+    }
+
+    let mut rank = vec![NONE; max_count * digits];
+    let mut first = vec![NONE; (len + 1) * width];
+    for level in 1..=max_len {
+        let mut keys: Vec<(u8, u32, usize)> = node_levels[level]
+            .iter()
+            .map(|&(j, k, p)| {
+                let rest = if p + 1 < cells[j].len() {
+                    rank[node(j, k, p + 1)]
+                } else if k == 1 {
+                    0
+                } else {
+                    let f = first[state(next(j), k - 1)] as usize;
+                    rank[node(f, k - 1, 0)]
+                };
+                (cells[j][p], rest, node(j, k, p))
+            })
+            .collect();
+        keys.sort_unstable();
+        let mut r = 0;
+        for (idx, &(digit, rest, id)) in keys.iter().enumerate() {
+            if idx > 0 && (digit, rest) != (keys[idx - 1].0, keys[idx - 1].1) {
+                r += 1;
+            }
+            rank[id] = r;
+        }
+
+        // Ties go to taking the cell, which keeps the choices early.
+        for &(i, k) in &state_levels[level] {
+            let take = tail_len(i, k) != NONE && cells[i].len() + tail_len(i, k) as usize == level;
+            let skip = length[state(i + 1, k)] as usize == level;
+            let pick = match (take, skip) {
+                (true, true) => {
+                    let other = first[state(i + 1, k)] as usize;
+                    if better(maximize, rank[node(other, k, 0)], rank[node(i, k, 0)]) {
+                        other
+                    } else {
+                        i
+                    }
+                }
+                (true, false) => i,
+                _ => first[state(i + 1, k)] as usize,
+            };
+            first[state(i, k)] = pick as u32;
+        }
+    }
+
+    // The numeric pass. value[i][k] is (digits, rank) of the best numeric
+    // value with nothing but zeros in front, where all zeros is (0, 0).
+    const SKIP: u8 = 1;
+    const TAKE_ZERO: u8 = 2;
+    const TAKE: u8 = 3;
+    let mut value = vec![(0, 0); (len + 1) * width];
+    let mut choice = vec![0u8; (len + 1) * width];
+    for i in (0..=len).rev() {
+        for k in 0..=max_count {
+            if k == 0 {
+                // Nothing left to turn on, which is a valid (zero) value.
+                choice[state(i, k)] = TAKE_ZERO;
+                continue;
+            }
+            if i == len {
+                continue;
+            }
+            let take = if bank[i] == 0 {
+                let s = state(next(i), k - 1);
+                (choice[s] != 0).then_some((value[s], TAKE_ZERO))
+            } else {
+                (tail_len(i, k) != NONE).then(|| {
+                    let level = cells[i].len() as u32 + tail_len(i, k);
+                    ((level, rank[node(i, k, 0)]), TAKE)
+                })
+            };
+            let s = state(i + 1, k);
+            let skip = (choice[s] != 0).then_some((value[s], SKIP));
+            let pick = match (take, skip) {
+                (Some(t), Some(s)) => Some(if better(maximize, s.0, t.0) { s } else { t }),
+                (t, s) => t.or(s),
+            };
+            if let Some((v, c)) = pick {
+                value[state(i, k)] = v;
+                choice[state(i, k)] = c;
+            }
+        }
+    }
+
+    let mut count = None;
+    for k in objective.min_count..=max_count {
+        if choice[state(0, k)] == 0 {
+            continue;
+        }
+        if count.is_none_or(|c| better(maximize, value[state(0, k)], value[state(0, c)])) {
+            count = Some(k);
+        }
+    }
+
+    // Walk the choices back from the start.
+    let (mut i, mut k) = (0, count?);
+    let mut indices = Vec::with_capacity(k);
+    while k > 0 {
+        match choice[state(i, k)] {
+            SKIP => i += 1,
+            TAKE_ZERO => {
+                indices.push(i);
+                i = next(i);
+                k -= 1;
+            }
+            _ => {
+                while k > 0 {
+                    let j = first[state(i, k)] as usize;
+                    indices.push(j);
+                    i = next(j);
+                    k -= 1;
+                }
+            }
+        }
+    }
+    Some(indices)
+}
+
+// Which batteries got turned on in a single bank, and the joltage they make.
+#[derive(Debug, PartialEq, Eq)]
+struct BankSelection {
+    indices: Vec<usize>,
+    joltage: String,
+}
+
+impl BankSelection {
+    fn new(bank: &[u64], n: usize) -> BankSelection {
+        BankSelection::with_objective(bank, &Objective::max(n))
+    }
+
+    // The stack works for single digits with no gaps, either for a fixed
+    // count or when maximizing, since turning on more batteries never
+    // lowers the joltage. Single digits with gaps are picked one at a time,
+    // and everything else goes through the DP.
+    fn with_objective(bank: &[u64], objective: &Objective) -> BankSelection {
+        let single = bank.iter().all(|v| *v < 10);
+        let spacing = objective.min_spacing.max(1);
+        let fixed = objective.min_count == objective.max_count;
+        let n = if objective.maximize && spacing == 1 {
+            objective.max_count.min(bank.len())
+        } else {
+            objective.max_count
+        };
+        let stack = single
+            && spacing == 1
+            && (fixed || objective.maximize)
+            && (objective.min_count..=bank.len()).contains(&n);
+        let indices = if stack {
+            Some(select_greedy(bank, n, objective.maximize))
+        } else if single && fixed {
+            select_spaced(bank, n, spacing, objective.maximize)
+        } else {
+            select_dp(bank, objective)
+        }
+        .expect("no way to pick batteries for this objective");
+        let joltage = indices.iter().map(|&idx| bank[idx].to_string()).collect();
+        BankSelection { indices, joltage }
+    }
+
+    // The original bank with the chosen batteries wrapped in brackets. Banks
+    // with multi-digit cells are shown with a space between the cells.
+    fn render(&self, bank: &[u64]) -> String {
+        let sep = if bank.iter().all(|v| *v < 10) {
+            ""
+        } else {
+            " "
+        };
+        let mut cells = Vec::new();
+        let mut chosen = self.indices.iter().peekable();
+        for (idx, value) in bank.iter().enumerate() {
+            if chosen.next_if_eq(&&idx).is_some() {
+                cells.push(format!("[{value}]"));
+            } else {
+                cells.push(value.to_string());
+            }
+        }
+        cells.join(sep)
+    }
+}
+
+fn select_banks(batteries: &[Vec<u64>], n: usize) -> Vec<BankSelection> {
+    batteries.iter().map(|b| BankSelection::new(b, n)).collect()
+}
+
+fn print_selections(batteries: &[Vec<u64>], n: usize) -> Vec<BankSelection> {
+    let selections = select_banks(batteries, n);
+    for (b, sel) in batteries.iter().zip(selections.iter()) {
+        println!("{} -> {} {:?}", sel.render(b), sel.joltage, sel.indices);
+    }
+    selections
+}
+
+// The same stack as select_greedy, run over the raw ASCII digits of a bank
+// and kept in the bank's own buffer: the stack can never grow past the digit
+// being read, so it just overwrites the front of the buffer. The chosen
+// digits end up in bank[..n].
+fn select_max_in_place(bank: &mut [u8], n: usize) -> &[u8] {
+    let mut removals = bank.len() - n;
+    let mut top = 0;
+
+    for idx in 0..bank.len() {
+        let digit = bank[idx];
+        while removals > 0 && top > 0 && bank[top - 1] < digit {
+            top -= 1;
+            removals -= 1;
+        }
+        bank[top] = digit;
+        top += 1;
+    }
+
+    &bank[..n]
+}
+
+// Work through the banks one line at a time straight from the file, without
+// ever holding more than a single bank in memory.
+fn compute_joltage_stream(filename: &str, n: usize) -> String {
+    let file = File::open(filename).unwrap();
+    let mut reader = BufReader::new(file);
+    let mut bank: Vec<u8> = Vec::new();
+    let mut total: Vec<u8> = Vec::new();
+
+    loop {
+        bank.clear();
+        if reader.read_until(b'\n', &mut bank).unwrap() == 0 {
+            break;
+        }
+        while bank.last().is_some_and(|c| c.is_ascii_whitespace()) {
+            bank.pop();
+        }
+        if bank.is_empty() {
+            continue;
+        }
+
+        let digits = select_max_in_place(&mut bank, n);
+        add_digits(&mut total, digits.iter().map(|c| (c - b'0') as u64));
+    }
+
+    let joltage = digits_to_string(&total);
+    println!("joltage: {joltage}");
+
+    joltage
+}
+
+// Banks where each cell can be more than one digit, e.g. "12,7,305".
+fn parse_cells(lines: &Vec<String>, delimiter: char) -> Vec<Vec<u64>> {
+    let mut batteries: Vec<_> = Vec::new();
+    for line in lines {
+        let row: Vec<u64> = line
+            .split(delimiter)
+            .map(|cell| cell.trim().parse().unwrap())
+            .collect();
+        batteries.push(row);
+    }
+    batteries
+}
+
+fn compute_joltage_objective(batteries: &Vec<Vec<u64>>, objective: &Objective) -> String {
+    let mut total: Vec<u8> = Vec::new();
+
+    for b in batteries {
+        let sel = BankSelection::with_objective(b, objective);
+        add_digits(&mut total, sel.joltage.bytes().map(|c| (c - b'0') as u64));
+    }
+
+    let joltage = digits_to_string(&total);
+    println!("joltage ({objective:?}): {joltage}");
+
+    joltage
+}
+
 fn compute_joltage_n(batteries: &Vec<Vec<u64>>, n: usize) -> u64 {
     let mut joltage = 0;
 
     for b in batteries {
         let mut jolt = 0;
-        let mut n_left = n;
-        let mut next_idx = 0;
-
-        while n_left > 0 {
-            let last_idx = b.len() - n_left + 1;
-            let mut hi_idx = next_idx;
-            let mut hi = b[hi_idx];
-            let mut idx = hi_idx + 1;
-            while idx < last_idx {
-                if b[idx] > hi {
-                    hi_idx = idx;
-                    hi = b[hi_idx];
-                }
-                idx += 1;
-            }
-
-            jolt = jolt * 10 + hi;
-            next_idx = hi_idx + 1;
-            n_left -= 1;
+        for idx in select_max(b, n) {
+            jolt = jolt * 10 + b[idx];
         }
 
         //println!("batteries: {b:?}");
@@ -103,37 +570,301 @@ fn compute_joltage_n(batteries: &Vec<Vec<u64>>, n: usize) -> u64 {
     joltage
 }
 
+// Add a number given as its decimal digits (most significant first) to a
+// running total kept as little-endian decimal digits.
+fn add_digits(total: &mut Vec<u8>, digits: impl DoubleEndedIterator<Item = u64>) {
+    let mut carry = 0;
+    let mut pos = 0;
+    for digit in digits.rev() {
+        if pos == total.len() {
+            total.push(0);
+        }
+        let sum = total[pos] as u64 + digit + carry;
+        total[pos] = (sum % 10) as u8;
+        carry = sum / 10;
+        pos += 1;
+    }
+    while carry > 0 {
+        if pos == total.len() {
+            total.push(0);
+        }
+        let sum = total[pos] as u64 + carry;
+        total[pos] = (sum % 10) as u8;
+        carry = sum / 10;
+        pos += 1;
+    }
+}
+
+fn digits_to_string(total: &[u8]) -> String {
+    let s: String = total
+        .iter()
+        .rev()
+        .skip_while(|d| **d == 0)
+        .map(|d| (b'0' + d) as char)
+        .collect();
+    if s.is_empty() {
+        "0".to_string()
+    } else {
+        s
+    }
+}
+
+// Same as compute_joltage_n, but keeps the total as a decimal string so that
+// n can go well past the 19 digits that fit in a u64.
+fn compute_joltage_big(batteries: &Vec<Vec<u64>>, n: usize) -> String {
+    let mut total: Vec<u8> = Vec::new();
+
+    for b in batteries {
+        add_digits(&mut total, select_max(b, n).into_iter().map(|idx| b[idx]));
+    }
+
+    let joltage = digits_to_string(&total);
+    println!("joltage: {joltage}");
+
+    joltage
+}
+
 #[test]
 fn test_prelim() {
-    let joltage = compute_joltage(&mut parse_lines(&get_input("prelim.txt")));
+    let joltage = compute_joltage(&parse_lines(&get_input("prelim.txt")));
     assert_eq!(joltage, 357);
-    let joltage = compute_joltage_n(&mut parse_lines(&get_input("prelim.txt")), 2);
+    let joltage = compute_joltage_n(&parse_lines(&get_input("prelim.txt")), 2);
     assert_eq!(joltage, 357);
 }
 
 #[test]
 fn test_part1() {
-    let joltage = compute_joltage(&mut parse_lines(&get_input("input.txt")));
+    let joltage = compute_joltage(&parse_lines(&get_input("input.txt")));
     assert_eq!(joltage, 17031);
-    let joltage = compute_joltage_n(&mut parse_lines(&get_input("input.txt")), 2);
+    let joltage = compute_joltage_n(&parse_lines(&get_input("input.txt")), 2);
     assert_eq!(joltage, 17031);
 }
 
 #[test]
 fn test_prelim2() {
-    let joltage = compute_joltage_n(&mut parse_lines(&get_input("prelim.txt")), 12);
+    let joltage = compute_joltage_n(&parse_lines(&get_input("prelim.txt")), 12);
     assert_eq!(joltage, 3121910778619);
 }
 
 #[test]
 fn test_part2() {
-    let joltage = compute_joltage_n(&mut parse_lines(&get_input("input.txt")), 12);
+    let joltage = compute_joltage_n(&parse_lines(&get_input("input.txt")), 12);
     assert_eq!(joltage, 168575096286051);
 }
 
+#[test]
+fn test_big() {
+    let joltage = compute_joltage_big(&parse_lines(&get_input("input.txt")), 12);
+    assert_eq!(joltage, "168575096286051");
+
+    // Long banks with thousands of batteries turned on, matched against the
+    // old windowed scan.
+    let mut seed = 12345u64;
+    let mut bank = Vec::new();
+    for _ in 0..5000 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        bank.push((seed >> 60) % 10);
+    }
+    let n = 3000;
+    let mut expected = String::new();
+    let mut next_idx = 0;
+    for n_left in (1..=n).rev() {
+        let last_idx = bank.len() - n_left + 1;
+        let mut hi_idx = next_idx;
+        for idx in next_idx..last_idx {
+            if bank[idx] > bank[hi_idx] {
+                hi_idx = idx;
+            }
+        }
+        expected.push(char::from_digit(bank[hi_idx] as u32, 10).unwrap());
+        next_idx = hi_idx + 1;
+    }
+    let joltage = compute_joltage_big(&vec![bank], n);
+    assert_eq!(joltage, expected.trim_start_matches('0'));
+}
+
+#[test]
+fn test_selections() {
+    let batteries = parse_lines(&get_input("prelim.txt"));
+    let selections = print_selections(&batteries, 2);
+    assert_eq!(selections[0].indices, vec![0, 1]);
+    assert_eq!(selections[0].joltage, "98");
+    assert_eq!(selections[0].render(&batteries[0]), "[9][8]7654321111111");
+    assert_eq!(selections[3].render(&batteries[3]), "818181[9]1111[2]111");
+
+    let selections = select_banks(&batteries, 12);
+    let total: u64 = selections
+        .iter()
+        .map(|s| s.joltage.parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(total, 3121910778619);
+    assert!(selections
+        .iter()
+        .all(|s| s.indices.windows(2).all(|w| w[0] < w[1])));
+}
+
+#[test]
+fn test_objectives() {
+    // Try every subset of a handful of small banks against each objective.
+    let banks = [
+        vec![3, 0, 9, 1, 0, 7, 2],
+        vec![0, 0, 5, 0, 1],
+        vec![12, 7, 305, 4, 99, 0],
+        vec![1, 10, 100, 0, 9],
+    ];
+    let objectives = [
+        Objective::max(3),
+        Objective::min(3),
+        Objective {
+            min_count: 1,
+            max_count: 4,
+            ..Objective::max(0)
+        },
+        Objective {
+            min_count: 1,
+            max_count: 4,
+            ..Objective::min(0)
+        },
+        Objective {
+            min_spacing: 2,
+            ..Objective::max(3)
+        },
+        Objective {
+            min_spacing: 3,
+            min_count: 1,
+            max_count: 3,
+            ..Objective::min(0)
+        },
+    ];
+    for bank in &banks {
+        for objective in &objectives {
+            let mut expected: Option<u128> = None;
+            for mask in 0u32..1 << bank.len() {
+                let indices: Vec<usize> =
+                    (0..bank.len()).filter(|i| mask & (1 << i) != 0).collect();
+                let count = indices.len();
+                if count < objective.min_count
+                    || count > objective.max_count
+                    || indices
+                        .windows(2)
+                        .any(|w| w[1] - w[0] < objective.min_spacing)
+                {
+                    continue;
+                }
+                let s: String = indices.iter().map(|&i| bank[i].to_string()).collect();
+                let value = s.parse::<u128>().unwrap();
+                expected = Some(match expected {
+                    None => value,
+                    Some(e) if objective.maximize => e.max(value),
+                    Some(e) => e.min(value),
+                });
+            }
+            let sel = BankSelection::with_objective(bank, objective);
+            assert_eq!(
+                sel.joltage.parse::<u128>().ok(),
+                expected,
+                "{bank:?} {objective:?}"
+            );
+            assert!(sel
+                .indices
+                .windows(2)
+                .all(|w| w[1] - w[0] >= objective.min_spacing));
+
+            // The DP has to agree even where a greedy pick gets used.
+            let dp = select_dp(bank, objective).map(|indices| {
+                let s: String = indices.iter().map(|&i| bank[i].to_string()).collect();
+                s.parse::<u128>().unwrap()
+            });
+            assert_eq!(dp, expected, "{bank:?} {objective:?}");
+        }
+    }
+
+    let cells = parse_cells(&get_input("prelim_cells.txt"), ',');
+    let sel = BankSelection::with_objective(&cells[0], &Objective::max(2));
+    assert_eq!(sel.joltage, "12305");
+    assert_eq!(sel.render(&cells[0]), "[12] 7 [305] 4");
+    let joltage = compute_joltage_objective(&cells, &Objective::max(2));
+    assert_eq!(joltage, (12305 + 8165 + 1005000).to_string());
+}
+
+#[test]
+fn test_long_objectives() {
+    // Long banks with gaps between the batteries, checked against the DP.
+    let mut seed = 3u64;
+    let bank: Vec<u64> = (0..5000)
+        .map(|_| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % 10
+        })
+        .collect();
+    let joltage =
+        |indices: &[usize]| -> String { indices.iter().map(|&i| bank[i].to_string()).collect() };
+    for objective in [
+        Objective {
+            min_spacing: 2,
+            ..Objective::max(100)
+        },
+        Objective {
+            min_spacing: 3,
+            ..Objective::min(100)
+        },
+        Objective {
+            min_count: 1,
+            max_count: 100,
+            ..Objective::max(0)
+        },
+    ] {
+        let sel = BankSelection::with_objective(&bank, &objective);
+        let dp = select_dp(&bank, &objective).unwrap();
+        assert_eq!(sel.joltage, joltage(&dp), "{objective:?}");
+    }
+
+    // Most of the bank, every other battery.
+    let sel = BankSelection::with_objective(
+        &bank,
+        &Objective {
+            min_spacing: 2,
+            ..Objective::max(2400)
+        },
+    );
+    assert_eq!(sel.indices.len(), 2400);
+    assert!(sel.indices.windows(2).all(|w| w[1] - w[0] >= 2));
+}
+
+#[test]
+fn test_stream() {
+    assert_eq!(compute_joltage_stream("prelim.txt", 2), "357");
+    assert_eq!(compute_joltage_stream("input.txt", 2), "17031");
+    assert_eq!(compute_joltage_stream("prelim.txt", 12), "3121910778619");
+    assert_eq!(compute_joltage_stream("input.txt", 12), "168575096286051");
+    assert_eq!(
+        compute_joltage_stream("input.txt", 40),
+        compute_joltage_big(&parse_lines(&get_input("input.txt")), 40)
+    );
+}
+
 fn main() {
-    compute_joltage(&mut parse_lines(&get_input("prelim.txt")));
-    compute_joltage(&mut parse_lines(&get_input("input.txt")));
-    compute_joltage_n(&mut parse_lines(&get_input("prelim.txt")), 12);
-    compute_joltage_n(&mut parse_lines(&get_input("input.txt")), 12);
+    compute_joltage(&parse_lines(&get_input("prelim.txt")));
+    compute_joltage(&parse_lines(&get_input("input.txt")));
+    compute_joltage_n(&parse_lines(&get_input("prelim.txt")), 12);
+    compute_joltage_n(&parse_lines(&get_input("input.txt")), 12);
+    compute_joltage_big(&parse_lines(&get_input("input.txt")), 50);
+    compute_joltage_stream("input.txt", 50);
+    print_selections(&parse_lines(&get_input("prelim.txt")), 12);
+    compute_joltage_objective(&parse_lines(&get_input("input.txt")), &Objective::min(12));
+    compute_joltage_objective(
+        &parse_lines(&get_input("prelim.txt")),
+        &Objective {
+            min_spacing: 2,
+            ..Objective::max(4)
+        },
+    );
+    compute_joltage_objective(
+        &parse_cells(&get_input("prelim_cells.txt"), ','),
+        &Objective::max(2),
+    );
 }
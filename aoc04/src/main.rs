@@ -13,10 +13,131 @@ fn get_input(filename: &str) -> Vec<String> {
     lines
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Neighborhood {
+    // Every cell within r steps, diagonals included (r = 1 is the usual 8).
+    Moore(usize),
+    // Every cell within r steps without cutting corners (r = 1 is the 4
+    // orthogonal neighbors).
+    VonNeumann(usize),
+    // Whatever (column, row) offsets are given.
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighborhood {
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Neighborhood::Moore(r) | Neighborhood::VonNeumann(r) => {
+                let r = *r as isize;
+                let mut offsets = Vec::new();
+                for dr in -r..=r {
+                    for dc in -r..=r {
+                        let in_range = match self {
+                            Neighborhood::VonNeumann(_) => dc.abs() + dr.abs() <= r,
+                            _ => true,
+                        };
+                        if (dc, dr) != (0, 0) && in_range {
+                            offsets.push((dc, dr));
+                        }
+                    }
+                }
+                offsets
+            }
+            Neighborhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+}
+
+// What a neighbor that falls off the edge of the grid looks like.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Boundary {
+    // Always empty, as if the floor carried on with nothing on it.
+    Empty,
+    // Wraps around to the other side, like a storage ring.
+    Wrap,
+    // Reflects back into the grid without repeating the edge itself, so one
+    // step past the left edge is the second column.
+    Mirror,
+    // Always holds a roll, as if the grid were walled in.
+    Occupied,
+}
+
+impl Boundary {
+    // Map a position `pos` steps into a `len` long row or column back into
+    // 0..len, or None if it stays outside.
+    fn fold(&self, pos: isize, len: usize) -> Option<usize> {
+        let len = len as isize;
+        if (0..len).contains(&pos) {
+            return Some(pos as usize);
+        }
+        match self {
+            Boundary::Empty | Boundary::Occupied => None,
+            Boundary::Wrap => Some(pos.rem_euclid(len) as usize),
+            Boundary::Mirror => {
+                let period = 2 * (len - 1);
+                if period == 0 {
+                    return Some(0);
+                }
+                let m = pos.rem_euclid(period);
+                Some(if m < len { m } else { period - m } as usize)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+// When a roll counts as available: it has to be one of the occupied
+// characters, and the number of occupied cells in its neighborhood has to
+// compare against the threshold.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Rule {
+    neighborhood: Neighborhood,
+    threshold: usize,
+    comparison: Comparison,
+    occupied: Vec<char>,
+    boundary: Boundary,
+}
+
+impl Default for Rule {
+    // The forklift rule from the puzzle: fewer than 4 of the 8 surrounding
+    // cells hold a roll.
+    fn default() -> Self {
+        Rule {
+            neighborhood: Neighborhood::Moore(1),
+            threshold: 4,
+            comparison: Comparison::Less,
+            occupied: vec!['@'],
+            boundary: Boundary::Empty,
+        }
+    }
+}
+
+impl Rule {
+    fn accepts(&self, num_used: usize) -> bool {
+        match self.comparison {
+            Comparison::Less => num_used < self.threshold,
+            Comparison::LessEqual => num_used <= self.threshold,
+            Comparison::Equal => num_used == self.threshold,
+            Comparison::GreaterEqual => num_used >= self.threshold,
+            Comparison::Greater => num_used > self.threshold,
+        }
+    }
+}
+
 struct Grid {
     rows: usize,
     cols: usize,
     elems: Box<[char]>,
+    rule: Rule,
+    offsets: Vec<(isize, isize)>,
 }
 
 impl Grid {
@@ -36,46 +157,81 @@ impl Grid {
         self.elems[self.get_idx(col, row)] = '.';
     }
 
-    pub fn create(lines: &Vec<String>) -> Self {
+    pub fn create(lines: &[String]) -> Self {
+        Self::create_with_rule(lines, Rule::default())
+    }
+
+    pub fn create_with_rule(lines: &[String], rule: Rule) -> Self {
         let rows = lines.len() + 2;
         let cols = lines[0].len() + 2;
         let mut elems: Box<[char]> = vec![' '; rows * cols].into_boxed_slice();
 
         // Use a trick to create a perimeter around the grid, so we can index without bounds checks
-        let mut row = 0;
-        for line in lines {
+        for (row, line) in lines.iter().enumerate() {
             line.char_indices().for_each(|(col, val)| {
                 elems[Self::helper_get_idx(col + 1, row + 1, cols)] = val;
             });
-            row += 1;
         }
 
         //println!("{elems:?}");
 
-        Grid { rows, cols, elems }
-    }
+        // Removed rolls are left behind as '.', which had better not still
+        // count as a roll.
+        if rule.occupied.contains(&'.') {
+            panic!("'.' can't be an occupied cell: {rule:?}");
+        }
 
-    pub fn is_available(&self, col: usize, row: usize) -> bool {
-        if self.get_elem(col, row) != '@' {
-            return false;
+        let offsets = rule.neighborhood.offsets();
+        Grid {
+            rows,
+            cols,
+            elems,
+            rule,
+            offsets,
         }
+    }
 
+    fn is_occupied(&self, col: usize, row: usize) -> bool {
+        self.rule.occupied.contains(&self.get_elem(col, row))
+    }
+
+    // The cell at an offset from (col, row), once the boundary rule has
+    // brought it back inside the grid. The perimeter only covers neighbors
+    // one step away, so this works on the inner grid directly, and None means
+    // the neighbor is off the edge.
+    fn neighbor(&self, col: usize, row: usize, dc: isize, dr: isize) -> Option<(usize, usize)> {
+        let boundary = self.rule.boundary;
+        let c = boundary.fold(col as isize - 1 + dc, self.cols - 2)?;
+        let r = boundary.fold(row as isize - 1 + dr, self.rows - 2)?;
+        Some((c + 1, r + 1))
+    }
+
+    fn neighbors(&self, col: usize, row: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.offsets
+            .iter()
+            .filter_map(move |&(dc, dr)| self.neighbor(col, row, dc, dr))
+    }
+
+    fn count_neighbors(&self, col: usize, row: usize) -> usize {
         let mut num_used = 0;
-        for (c, r) in [
-            (col - 1, row - 1),
-            (col, row - 1),
-            (col + 1, row - 1),
-            (col - 1, row),
-            (col + 1, row),
-            (col - 1, row + 1),
-            (col, row + 1),
-            (col + 1, row + 1),
-        ] {
-            if self.get_elem(c, r) == '@' {
+        for &(dc, dr) in &self.offsets {
+            let used = match self.neighbor(col, row, dc, dr) {
+                Some((c, r)) => self.is_occupied(c, r),
+                None => self.rule.boundary == Boundary::Occupied,
+            };
+            if used {
                 num_used += 1;
             }
         }
-        num_used < 4
+        num_used
+    }
+
+    pub fn is_available(&self, col: usize, row: usize) -> bool {
+        if !self.is_occupied(col, row) {
+            return false;
+        }
+
+        self.rule.accepts(self.count_neighbors(col, row))
     }
 
     pub fn get_rolls(&self) -> usize {
@@ -93,28 +249,333 @@ impl Grid {
         available_rolls
     }
 
-    pub fn helper_remove_rolls(&mut self) -> usize {
-        let mut available_rolls = 0;
+    // Rather than rescanning the whole grid until nothing changes, keep a
+    // count of the occupied neighbors for every roll, and only look again at
+    // the rolls whose count changed in the last wave. Every roll available
+    // at the start of a wave goes out together, so a roll that wasn't
+    // available before a wave and whose count didn't change can't be
+    // available after it either.
+    //
+    // For the usual "fewer than" rules a roll is checked at most a couple of
+    // times, since removals only ever lower counts. Rules like "more than"
+    // work the same way, except that a removal can take a roll back out of
+    // contention.
+    pub fn remove_rolls_history(&mut self) -> RemovalHistory {
+        let mut counts = vec![0; self.elems.len()];
+        let mut generations = vec![None; self.elems.len()];
+        // The last wave each roll was queued up for, so it only gets
+        // checked once per wave.
+        let mut queued = vec![0; self.elems.len()];
+        // Which rolls count each cell as a neighbor, once per time they do.
+        // Offsets don't have to be symmetric, so these are found by walking
+        // the offsets backwards. That only works while the boundary can be
+        // undone, and a mirrored edge can see the same cell twice, so for
+        // mirrors the list is built up front instead.
+        let mirrored = self.rule.boundary == Boundary::Mirror;
+        let mut mirror_watchers: Vec<Vec<(usize, usize)>> = Vec::new();
+        if mirrored {
+            mirror_watchers = vec![Vec::new(); self.elems.len()];
+        }
+        let mut candidates = Vec::new();
         for row in 1..self.rows - 1 {
             for col in 1..self.cols - 1 {
-                if self.is_available(col, row) {
-                    available_rolls += 1;
-                    self.remove_roll(col, row);
+                if self.is_occupied(col, row) {
+                    counts[self.get_idx(col, row)] = self.count_neighbors(col, row);
+                    candidates.push((col, row));
+                    if mirrored {
+                        for (c, r) in self.neighbors(col, row) {
+                            mirror_watchers[self.get_idx(c, r)].push((col, row));
+                        }
+                    }
+                }
+            }
+        }
+
+        let initial = candidates.clone();
+        let mut watchers = Vec::new();
+        let mut wave_sizes: Vec<usize> = Vec::new();
+        let mut wave = 1;
+        loop {
+            let removals: Vec<(usize, usize)> = candidates
+                .drain(..)
+                .filter(|&(col, row)| {
+                    self.is_occupied(col, row) && self.rule.accepts(counts[self.get_idx(col, row)])
+                })
+                .collect();
+            if removals.is_empty() {
+                break;
+            }
+
+            for &(col, row) in &removals {
+                self.remove_roll(col, row);
+                generations[self.get_idx(col, row)] = Some(wave);
+            }
+            for &(col, row) in &removals {
+                watchers.clear();
+                if mirrored {
+                    watchers.extend_from_slice(&mirror_watchers[self.get_idx(col, row)]);
+                } else {
+                    watchers.extend(
+                        self.offsets
+                            .iter()
+                            .filter_map(|&(dc, dr)| self.neighbor(col, row, -dc, -dr)),
+                    );
+                }
+                for &(c, r) in &watchers {
+                    if self.is_occupied(c, r) {
+                        let idx = self.get_idx(c, r);
+                        counts[idx] -= 1;
+                        if queued[idx] != wave {
+                            queued[idx] = wave;
+                            candidates.push((c, r));
+                        }
+                    }
+                }
+            }
+
+            wave_sizes.push(removals.len());
+            wave += 1;
+        }
+
+        // Every roll is a candidate to begin with, so whichever of those
+        // never got a wave are the ones that can't be removed.
+        let never_removed = initial
+            .into_iter()
+            .filter(|&(col, row)| generations[self.get_idx(col, row)].is_none())
+            .collect();
+
+        RemovalHistory {
+            cols: self.cols,
+            generations,
+            wave_sizes,
+            never_removed,
+        }
+    }
+
+    pub fn remove_rolls(&mut self) -> usize {
+        let history = self.remove_rolls_history();
+        let available_rolls = history.wave_sizes.iter().sum();
+
+        println!("Total removable rolls: {available_rolls}");
+
+        available_rolls
+    }
+
+    // Lay the removal generations over the grid: the wave number for each
+    // removed roll (1-9, then a-z), and the original contents everywhere
+    // else, including the rolls that never come out.
+    pub fn render_generations(&self, history: &RemovalHistory) -> String {
+        let mut s = String::new();
+        for row in 1..self.rows - 1 {
+            for col in 1..self.cols - 1 {
+                let c = match history.generation(col, row) {
+                    Some(wave) if wave < 36 => std::char::from_digit(wave as u32, 36).unwrap(),
+                    Some(_) => '+',
+                    None => self.get_elem(col, row),
+                };
+                s.push(c);
+            }
+            s.push('\n');
+        }
+        s
+    }
+}
+
+// Which wave each roll was removed in, using the same padded coordinates as
+// the grid, how many rolls went out in each wave, and the rolls that never
+// went out at all.
+struct RemovalHistory {
+    cols: usize,
+    generations: Vec<Option<usize>>,
+    wave_sizes: Vec<usize>,
+    never_removed: Vec<(usize, usize)>,
+}
+
+impl RemovalHistory {
+    pub fn generation(&self, col: usize, row: usize) -> Option<usize> {
+        self.generations[Grid::helper_get_idx(col, row, self.cols)]
+    }
+
+    pub fn num_waves(&self) -> usize {
+        self.wave_sizes.len()
+    }
+
+    pub fn never_removable(&self) -> &[(usize, usize)] {
+        &self.never_removed
+    }
+}
+
+// One bit per cell, 64 cells to a word, for floors too big to keep as
+// chars. This only handles the puzzle's own rule (a roll with fewer than 4 of
+// its 8 neighbors occupied, and nothing past the edges), but counts the
+// neighbors for a whole word of cells at once.
+struct BitGrid {
+    rows: usize,
+    cols: usize,
+    words: usize,
+    bits: Vec<u64>,
+}
+
+// Add one more bitmask into a bit-sliced counter, where planes[i] holds bit i
+// of the count for each cell. Four planes are plenty for 8 neighbors.
+fn add_to_count(planes: &mut [u64; 4], mut carry: u64) {
+    for plane in planes.iter_mut() {
+        let next = *plane & carry;
+        *plane ^= carry;
+        carry = next;
+    }
+}
+
+impl BitGrid {
+    fn new(rows: usize, cols: usize) -> Self {
+        let words = cols.div_ceil(64);
+        BitGrid {
+            rows,
+            cols,
+            words,
+            bits: vec![0; rows * words],
+        }
+    }
+
+    fn set_row(&mut self, row: usize, line: &str) {
+        let words = self.words;
+        let bits = &mut self.bits[row * words..(row + 1) * words];
+        for (col, c) in line.bytes().enumerate() {
+            if c == b'@' {
+                bits[col / 64] |= 1 << (col % 64);
+            }
+        }
+    }
+
+    pub fn create(lines: &[String]) -> Self {
+        let mut grid = BitGrid::new(lines.len(), lines[0].len());
+        for (row, line) in lines.iter().enumerate() {
+            grid.set_row(row, line);
+        }
+        grid
+    }
+
+    // Build the grid straight from the file, so the text never has to sit in
+    // memory all at once.
+    pub fn from_file(filename: &str) -> Self {
+        let reader = BufReader::new(File::open(filename).unwrap());
+        let mut grid = BitGrid::new(0, 0);
+        for line in reader.lines() {
+            let line = line.unwrap();
+            if grid.rows == 0 {
+                grid = BitGrid::new(0, line.len());
+            } else if line.len() != grid.cols {
+                panic!("Ragged row {}: {line}", grid.rows);
+            }
+            grid.bits.resize((grid.rows + 1) * grid.words, 0);
+            grid.set_row(grid.rows, &line);
+            grid.rows += 1;
+        }
+        grid
+    }
+
+    pub fn get_elem(&self, col: usize, row: usize) -> bool {
+        self.bits[row * self.words + col / 64] & (1 << (col % 64)) != 0
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        (0..self.rows)
+            .map(|row| {
+                (0..self.cols)
+                    .map(|col| if self.get_elem(col, row) { '@' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.bits[row * self.words..(row + 1) * self.words]
+    }
+
+    // The rolls in `cur` that are available, given the rows above and below
+    // it (None past the top or bottom edge).
+    fn available(&self, above: Option<&[u64]>, cur: &[u64], below: Option<&[u64]>) -> Vec<u64> {
+        let words = self.words;
+        let mut avail = vec![0; words];
+        for k in 0..words {
+            let mut planes = [0u64; 4];
+            for (row, is_cur) in [(above, false), (Some(cur), true), (below, false)] {
+                let Some(row) = row else {
+                    continue;
+                };
+                // Line each cell up with its left and right neighbors,
+                // carrying bits across word boundaries.
+                let west = (row[k] << 1) | if k > 0 { row[k - 1] >> 63 } else { 0 };
+                let east = (row[k] >> 1) | if k + 1 < words { row[k + 1] << 63 } else { 0 };
+                add_to_count(&mut planes, west);
+                add_to_count(&mut planes, east);
+                if !is_cur {
+                    add_to_count(&mut planes, row[k]);
                 }
             }
+            // Fewer than 4 means neither the 4s nor the 8s bit is set.
+            avail[k] = cur[k] & !(planes[2] | planes[3]);
+        }
+        avail
+    }
+
+    pub fn get_rolls(&self) -> usize {
+        let mut available_rolls = 0;
+        for row in 0..self.rows {
+            let above = (row > 0).then(|| self.row(row - 1));
+            let below = (row + 1 < self.rows).then(|| self.row(row + 1));
+            let avail = self.available(above, self.row(row), below);
+            available_rolls += avail.iter().map(|w| w.count_ones() as usize).sum::<usize>();
         }
 
+        println!("available_rolls: {available_rolls}");
+
         available_rolls
     }
 
+    // Remove rolls a wave at a time. Each wave only revisits rows next to one
+    // that changed in the wave before, and removals are applied as we go, so
+    // the row above is kept around as it was before its own removals.
     pub fn remove_rolls(&mut self) -> usize {
+        let words = self.words;
+        let mut dirty = vec![true; self.rows];
         let mut available_rolls = 0;
+
         loop {
-            let more_rolls = self.helper_remove_rolls();
-            if more_rolls == 0 {
+            let mut next_dirty = vec![false; self.rows];
+            let mut prev: Option<(usize, Vec<u64>)> = None;
+            let mut removed = 0;
+
+            for (row, &is_dirty) in dirty.iter().enumerate() {
+                if !is_dirty {
+                    continue;
+                }
+                let above = match &prev {
+                    Some((r, bits)) if *r + 1 == row => Some(bits.as_slice()),
+                    _ => (row > 0).then(|| self.row(row - 1)),
+                };
+                let below = (row + 1 < self.rows).then(|| self.row(row + 1));
+                let avail = self.available(above, self.row(row), below);
+                let count: usize = avail.iter().map(|w| w.count_ones() as usize).sum();
+                if count == 0 {
+                    prev = None;
+                    continue;
+                }
+
+                prev = Some((row, self.row(row).to_vec()));
+                let bits = &mut self.bits[row * words..(row + 1) * words];
+                for (b, a) in bits.iter_mut().zip(avail.iter()) {
+                    *b &= !a;
+                }
+                removed += count;
+                next_dirty[row.saturating_sub(1)..(row + 2).min(self.rows)].fill(true);
+            }
+
+            if removed == 0 {
                 break;
             }
-            available_rolls += more_rolls;
+            available_rolls += removed;
+            dirty = next_dirty;
         }
 
         println!("Total removable rolls: {available_rolls}");
@@ -151,11 +612,353 @@ fn test_part2() {
     assert_eq!(rolls, 8701);
 }
 
+// A pseudo-random floor where each cell holds a roll with probability
+// density / 10.
+#[cfg(test)]
+fn random_floor(rows: usize, cols: usize, density: u64, seed: u64) -> Vec<String> {
+    let mut seed = seed;
+    let mut lines = Vec::new();
+    for _ in 0..rows {
+        let mut line = String::new();
+        for _ in 0..cols {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let occupied = (seed >> 33) % 10 < density;
+            line.push(if occupied { '@' } else { '.' });
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[test]
+fn test_large_warehouse() {
+    // A big, mostly full floor that takes a lot of waves to clear out.
+    let lines = random_floor(400, 400, 8, 42);
+
+    let mut grid = Grid::create(&lines);
+    let rolls = grid.remove_rolls();
+
+    // Every roll left over must still be stuck, and nothing else was removed.
+    let expected = Grid::create(&lines);
+    let mut remaining = 0;
+    for row in 1..grid.rows - 1 {
+        for col in 1..grid.cols - 1 {
+            assert!(!grid.is_available(col, row));
+            if grid.get_elem(col, row) == '@' {
+                remaining += 1;
+            }
+        }
+    }
+    let total = expected.elems.iter().filter(|c| **c == '@').count();
+    assert_eq!(rolls + remaining, total);
+}
+
+#[test]
+fn test_waves() {
+    let mut grid = Grid::create(&get_input("prelim.txt"));
+    let history = grid.remove_rolls_history();
+    assert_eq!(history.wave_sizes, vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+    assert_eq!(history.num_waves(), 9);
+    assert_eq!(history.generation(3, 1), Some(1));
+    assert_eq!(history.generation(1, 1), None);
+
+    // Whatever is still on the floor afterwards is never removable.
+    let stuck = history.never_removable();
+    assert_eq!(stuck.len(), 71 - 43);
+    assert!(stuck
+        .iter()
+        .all(|&(c, r)| history.generation(c, r).is_none()));
+
+    let map = grid.render_generations(&history);
+    assert_eq!(map.lines().next(), Some("..11.1121."));
+    assert_eq!(map.matches('@').count(), stuck.len());
+}
+
+// Check the worklist against a simple rescan of the whole grid until nothing
+// changes.
+#[cfg(test)]
+fn check_against_rescan(lines: &[String], rule: Rule) {
+    let mut grid = Grid::create_with_rule(lines, rule.clone());
+    let history = grid.remove_rolls_history();
+
+    let mut expected = Grid::create_with_rule(lines, rule.clone());
+    let mut expected_waves = Vec::new();
+    loop {
+        let mut wave = Vec::new();
+        for row in 1..expected.rows - 1 {
+            for col in 1..expected.cols - 1 {
+                if expected.is_available(col, row) {
+                    wave.push((col, row));
+                }
+            }
+        }
+        if wave.is_empty() {
+            break;
+        }
+        expected_waves.push(wave.len());
+        for (col, row) in wave {
+            expected.remove_roll(col, row);
+        }
+    }
+    assert_eq!(history.wave_sizes, expected_waves, "{rule:?}");
+    assert_eq!(grid.elems, expected.elems);
+}
+
+#[test]
+fn test_rules() {
+    let lines = get_input("prelim.txt");
+
+    // Spelling out the default rule by hand changes nothing.
+    let rule = Rule {
+        neighborhood: Neighborhood::Custom(Neighborhood::Moore(1).offsets()),
+        comparison: Comparison::LessEqual,
+        threshold: 3,
+        ..Rule::default()
+    };
+    let mut grid = Grid::create_with_rule(&lines, rule);
+    assert_eq!(grid.get_rolls(), 13);
+    assert_eq!(grid.remove_rolls(), 43);
+
+    assert_eq!(Neighborhood::Moore(2).offsets().len(), 24);
+    assert_eq!(Neighborhood::VonNeumann(1).offsets().len(), 4);
+    assert_eq!(Neighborhood::VonNeumann(2).offsets().len(), 12);
+
+    // Compare every rule against a rescan.
+    let rules = [
+        Rule {
+            neighborhood: Neighborhood::VonNeumann(1),
+            threshold: 2,
+            ..Rule::default()
+        },
+        Rule {
+            neighborhood: Neighborhood::Moore(2),
+            threshold: 12,
+            ..Rule::default()
+        },
+        Rule {
+            neighborhood: Neighborhood::VonNeumann(2),
+            threshold: 6,
+            comparison: Comparison::GreaterEqual,
+            ..Rule::default()
+        },
+        Rule {
+            neighborhood: Neighborhood::Moore(1),
+            threshold: 5,
+            comparison: Comparison::Greater,
+            ..Rule::default()
+        },
+        Rule {
+            threshold: 5,
+            comparison: Comparison::Equal,
+            occupied: vec!['@', '#'],
+            ..Rule::default()
+        },
+    ];
+    for rule in rules {
+        check_against_rescan(&lines, rule);
+    }
+
+    // One-sided offsets: each roll only looks right and down.
+    check_against_rescan(
+        &lines,
+        Rule {
+            neighborhood: Neighborhood::Custom(vec![(1, 0), (1, 1), (0, 1)]),
+            threshold: 2,
+            ..Rule::default()
+        },
+    );
+
+    // Rolls that never come out are found by whatever marks them.
+    let lines: Vec<String> = vec!["#####".to_string(); 5];
+    let rule = Rule {
+        occupied: vec!['#'],
+        ..Rule::default()
+    };
+    let mut grid = Grid::create_with_rule(&lines, rule);
+    let history = grid.remove_rolls_history();
+    assert_eq!(history.wave_sizes, vec![4]);
+    assert_eq!(history.never_removable().len(), 21);
+}
+
+#[test]
+fn test_boundaries() {
+    assert_eq!(Boundary::Wrap.fold(-1, 5), Some(4));
+    assert_eq!(Boundary::Wrap.fold(7, 5), Some(2));
+    assert_eq!(Boundary::Mirror.fold(-1, 5), Some(1));
+    assert_eq!(Boundary::Mirror.fold(5, 5), Some(3));
+    assert_eq!(Boundary::Mirror.fold(-6, 5), Some(2));
+    assert_eq!(Boundary::Empty.fold(-1, 5), None);
+    assert_eq!(Boundary::Occupied.fold(4, 5), Some(4));
+
+    // A hollow ring of rolls.
+    let lines: Vec<String> = ["@@@@", "@..@", "@@@@"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let with = |boundary| Rule {
+        boundary,
+        ..Rule::default()
+    };
+
+    // The corners have only 2 neighbors inside the grid, but 7 when it wraps
+    // around on itself or is walled in, and 4 when the edges are mirrored.
+    let grid = Grid::create_with_rule(&lines, with(Boundary::Empty));
+    assert_eq!(grid.count_neighbors(1, 1), 2);
+    assert_eq!(grid.get_rolls(), 8);
+    let grid = Grid::create_with_rule(&lines, with(Boundary::Wrap));
+    assert_eq!(grid.count_neighbors(1, 1), 7);
+    assert_eq!(grid.get_rolls(), 0);
+    let grid = Grid::create_with_rule(&lines, with(Boundary::Occupied));
+    assert_eq!(grid.count_neighbors(1, 1), 7);
+    assert_eq!(grid.get_rolls(), 0);
+    let grid = Grid::create_with_rule(&lines, with(Boundary::Mirror));
+    assert_eq!(grid.count_neighbors(1, 1), 4);
+    assert_eq!(grid.get_rolls(), 0);
+
+    // Walled in floors can only lose rolls, never gain neighbors, so they
+    // clear out less than open ones.
+    let lines = get_input("prelim.txt");
+    let mut open = Grid::create_with_rule(&lines, with(Boundary::Empty));
+    let mut walled = Grid::create_with_rule(&lines, with(Boundary::Occupied));
+    let mut ring = Grid::create_with_rule(&lines, with(Boundary::Wrap));
+    assert_eq!(open.remove_rolls(), 43);
+    assert!(walled.remove_rolls() <= 43);
+    assert!(ring.remove_rolls() <= 43);
+
+    // Mirrored edges see the second column twice, and wrapping a narrow grid
+    // sees the same cell from both sides, so the worklist has to agree with
+    // a rescan on those too.
+    for boundary in [
+        Boundary::Empty,
+        Boundary::Wrap,
+        Boundary::Mirror,
+        Boundary::Occupied,
+    ] {
+        check_against_rescan(&lines, with(boundary));
+        check_against_rescan(&get_input("input.txt"), with(boundary));
+    }
+    let narrow: Vec<String> = ["@@", "@.", "@@", ".@", "@@"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    for boundary in [Boundary::Wrap, Boundary::Mirror] {
+        for threshold in 1..8 {
+            let rule = Rule {
+                threshold,
+                ..with(boundary)
+            };
+            check_against_rescan(&narrow, rule.clone());
+            let rule = Rule {
+                neighborhood: Neighborhood::Moore(2),
+                threshold: threshold * 3,
+                ..with(boundary)
+            };
+            check_against_rescan(&narrow, rule);
+        }
+    }
+}
+
+#[test]
+fn test_bit_grid() {
+    let lines = get_input("prelim.txt");
+    let grid = BitGrid::create(&lines);
+    assert!(grid.get_elem(2, 0));
+    assert!(!grid.get_elem(0, 0));
+    assert_eq!(grid.get_rolls(), 13);
+    let mut grid = BitGrid::from_file("prelim.txt");
+    assert_eq!(grid.remove_rolls(), 43);
+
+    let mut grid = BitGrid::from_file("input.txt");
+    assert_eq!(grid.get_rolls(), 1451);
+    assert_eq!(grid.remove_rolls(), 8701);
+
+    // Widths either side of a word boundary, checked against the char grid.
+    for cols in [1, 63, 64, 65, 130] {
+        let lines = random_floor(50, cols, 7, cols as u64);
+
+        let mut expected = Grid::create(&lines);
+        let mut grid = BitGrid::create(&lines);
+        assert_eq!(grid.get_rolls(), expected.get_rolls());
+        assert_eq!(grid.remove_rolls(), expected.remove_rolls());
+        for (row, line) in grid.to_lines().iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                assert_eq!(c, expected.get_elem(col + 1, row + 1));
+            }
+        }
+    }
+}
+
 fn main() {
     let mut grid = Grid::create(&get_input("prelim.txt"));
     grid.get_rolls();
     grid.remove_rolls();
+    let mut grid = Grid::create(&get_input("prelim.txt"));
+    let history = grid.remove_rolls_history();
+    println!("waves: {:?}", history.wave_sizes);
+    print!("{}", grid.render_generations(&history));
+    println!(
+        "never removable: {} rolls, last wave {}",
+        history.never_removable().len(),
+        history.num_waves()
+    );
     let mut grid = Grid::create(&get_input("input.txt"));
     grid.get_rolls();
     grid.remove_rolls();
+
+    let mut grid = BitGrid::create(&get_input("prelim.txt"));
+    grid.remove_rolls();
+    for line in grid.to_lines() {
+        println!("{line}");
+    }
+    let mut grid = BitGrid::from_file("input.txt");
+    grid.get_rolls();
+    grid.remove_rolls();
+
+    // A few other forklift rules on the same floor.
+    let knight_moves = vec![
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ];
+    for (neighborhood, comparison, threshold) in [
+        (Neighborhood::VonNeumann(1), Comparison::Less, 2),
+        (Neighborhood::Moore(2), Comparison::LessEqual, 10),
+        (Neighborhood::Custom(knight_moves), Comparison::Equal, 4),
+        (Neighborhood::Moore(1), Comparison::GreaterEqual, 7),
+        (Neighborhood::VonNeumann(2), Comparison::Greater, 10),
+    ] {
+        let rule = Rule {
+            neighborhood,
+            comparison,
+            threshold,
+            ..Rule::default()
+        };
+        println!("{rule:?}");
+        let mut grid = Grid::create_with_rule(&get_input("input.txt"), rule);
+        grid.get_rolls();
+        grid.remove_rolls();
+    }
+
+    for boundary in [
+        Boundary::Empty,
+        Boundary::Wrap,
+        Boundary::Mirror,
+        Boundary::Occupied,
+    ] {
+        println!("{boundary:?}");
+        let rule = Rule {
+            boundary,
+            ..Rule::default()
+        };
+        let mut grid = Grid::create_with_rule(&get_input("input.txt"), rule);
+        grid.get_rolls();
+        grid.remove_rolls();
+    }
 }
@@ -1,3 +1,6 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
@@ -13,7 +16,7 @@ fn get_input(filename: &str) -> Vec<String> {
     lines
 }
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 struct IngredientRange {
     start: usize,
     stop: usize,
@@ -24,7 +27,7 @@ impl IngredientRange {
         IngredientRange { start, stop }
     }
 
-    pub fn parse_list(lines: &Vec<String>) -> (Vec<Self>, Vec<usize>) {
+    pub fn parse_list(lines: &[String]) -> (Vec<Self>, Vec<usize>) {
         let mut ranges = Vec::new();
         let mut ingredients = Vec::new();
 
@@ -48,57 +51,485 @@ impl IngredientRange {
         (ranges, ingredients)
     }
 
-    pub fn merge_ranges(ranges: &mut Vec<IngredientRange>) -> usize {
-        ranges.sort();
-
-        // This isn't the nicest way to merge these ranges, but it is still pretty fast
-        let mut i = 0;
-        while i < ranges.len() {
-            let j = i + 1;
-            if j < ranges.len() {
-                let ri = &ranges[i];
-                let rj = &ranges[j];
-                if rj.start <= ri.stop {
-                    if rj.stop > ri.stop {
-                        ranges[i].stop = ranges[j].stop;
-                    }
-                    ranges.remove(j);
-                    continue;
+    // Number of IDs in the range. 0-usize::MAX holds one more ID than fits
+    // in a usize, so this comes back as a u128.
+    pub fn len(&self) -> u128 {
+        self.stop as u128 - self.start as u128 + 1
+    }
+
+    // Sort the ranges and fold any that overlap into one another, building
+    // up a new list in a single pass.
+    pub fn merge(ranges: &[IngredientRange]) -> Vec<IngredientRange> {
+        Self::merge_with(ranges, false)
+    }
+
+    // Same as merge, but optionally also joins up ranges that only touch,
+    // so 5-7 and 8-9 become 5-9.
+    pub fn merge_with(ranges: &[IngredientRange], coalesce_adjacent: bool) -> Vec<IngredientRange> {
+        let mut sorted = ranges.to_vec();
+        sorted.sort();
+
+        let mut merged: Vec<IngredientRange> = Vec::with_capacity(sorted.len());
+        for range in sorted {
+            match merged.last_mut() {
+                Some(last)
+                    if range.start <= last.stop
+                        || (coalesce_adjacent && range.start - 1 == last.stop) =>
+                {
+                    last.stop = last.stop.max(range.stop);
                 }
-             }
-            i += 1;
+                _ => merged.push(range),
+            }
         }
-        //println!("ranges {ranges:?}");
+        //println!("ranges {merged:?}");
 
-        let mut sum = 0;
-        for range in ranges {
-            sum += 1 + range.stop - range.start;
-        }
+        merged
+    }
+
+    // How many ingredient IDs the ranges cover, which only works out if they
+    // have already been merged.
+    pub fn coverage(merged: &[IngredientRange]) -> u128 {
+        merged.iter().map(|range| range.len()).sum()
+    }
+
+    pub fn merge_ranges(ranges: &mut Vec<IngredientRange>) -> u128 {
+        *ranges = Self::merge(ranges);
+        let sum = Self::coverage(ranges);
 
         println!("sum {sum}");
         sum
     }
 }
 
-fn compute_fresh((ranges, ingredients): &(Vec<IngredientRange>, Vec<usize>)) -> usize {
-    let mut fresh = 0;
-
-    //println!("{ranges:?}");
-    //println!("{ingredients:?}");
-    for ingredient in ingredients {
-        for range in ranges {
-            if ingredient >= &range.start && ingredient <= &range.stop {
-                fresh += 1;
+// A set of ingredient IDs kept as sorted, non-overlapping ranges, which can
+// be searched instead of scanning every range for every ingredient, and
+// combined with other sets a range at a time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct IntervalSet {
+    ranges: Vec<IngredientRange>,
+}
+
+impl IntervalSet {
+    pub fn new(ranges: &[IngredientRange]) -> Self {
+        IntervalSet {
+            ranges: IngredientRange::merge(ranges),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // Like new, but with touching ranges joined up too.
+    pub fn coalesced(ranges: &[IngredientRange]) -> Self {
+        IntervalSet {
+            ranges: IngredientRange::merge_with(ranges, true),
+        }
+    }
+
+    pub fn coverage(&self) -> u128 {
+        IngredientRange::coverage(&self.ranges)
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = self.ranges.clone();
+        ranges.extend_from_slice(&other.ranges);
+        IntervalSet::new(&ranges)
+    }
+
+    // Walk both lists together, keeping whatever overlaps and moving past
+    // whichever range finishes first.
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let a = self.ranges[i];
+            let b = other.ranges[j];
+            let start = a.start.max(b.start);
+            let stop = a.stop.min(b.stop);
+            if start <= stop {
+                ranges.push(IngredientRange::new(start, stop));
+            }
+            if a.stop < b.stop {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    // Everything between lo and hi (inclusive) that isn't in the set.
+    pub fn complement(&self, lo: usize, hi: usize) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let mut next = Some(lo);
+        for range in &self.ranges {
+            let Some(start) = next else {
+                break;
+            };
+            if range.stop < start {
+                continue;
+            }
+            if range.start > hi {
+                break;
+            }
+            if range.start > start {
+                ranges.push(IngredientRange::new(start, range.start - 1));
+            }
+            next = range.stop.checked_add(1);
+        }
+        if let Some(start) = next {
+            if start <= hi {
+                ranges.push(IngredientRange::new(start, hi));
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        match (self.ranges.first(), self.ranges.last()) {
+            (Some(first), Some(last)) => {
+                self.intersection(&other.complement(first.start, last.stop))
+            }
+            _ => IntervalSet { ranges: Vec::new() },
+        }
+    }
+
+    pub fn symmetric_difference(&self, other: &IntervalSet) -> IntervalSet {
+        self.difference(other).union(&other.difference(self))
+    }
+
+    // Whether every ID in the range is in the set, which means it has to sit
+    // inside a single one of the merged ranges.
+    pub fn contains_range(&self, range: &IngredientRange) -> bool {
+        let idx = self.ranges.partition_point(|r| r.start <= range.start);
+        idx > 0 && range.stop <= self.ranges[idx - 1].stop
+    }
+
+    pub fn is_superset(&self, other: &IntervalSet) -> bool {
+        other.ranges.iter().all(|r| self.contains_range(r))
+    }
+
+    pub fn overlaps(&self, other: &IntervalSet) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    // The runs of IDs between one range and the next.
+    pub fn gaps(&self) -> impl Iterator<Item = IngredientRange> + '_ {
+        self.ranges.windows(2).filter_map(|w| {
+            if w[0].stop + 1 < w[1].start {
+                Some(IngredientRange::new(w[0].stop + 1, w[1].start - 1))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Binary search for the last range starting at or before the ingredient,
+    // which is the only one that could hold it.
+    pub fn contains(&self, ingredient: usize) -> bool {
+        let idx = self.ranges.partition_point(|r| r.start <= ingredient);
+        idx > 0 && ingredient <= self.ranges[idx - 1].stop
+    }
+
+    // For ingredients that are already sorted, walk them alongside the ranges
+    // instead of searching for each one.
+    pub fn count_sorted(&self, ingredients: &[usize]) -> usize {
+        let mut fresh = 0;
+        let mut ranges = self.ranges.iter().peekable();
+        for &ingredient in ingredients {
+            while ranges.next_if(|r| r.stop < ingredient).is_some() {}
+            match ranges.peek() {
+                Some(r) if r.start <= ingredient => fresh += 1,
+                Some(_) => {}
+                None => break,
+            }
+        }
+        fresh
+    }
+}
+
+// An IntervalSet that can be changed a range at a time, for when the list of
+// fresh ranges keeps moving. The merged ranges live in a BTreeMap from start
+// to stop, so an update only has to look at the ranges it touches, each of
+// which it either leaves alone or removes for good.
+#[derive(Debug, Default)]
+struct DynamicIntervalSet {
+    ranges: BTreeMap<usize, usize>,
+    coverage: u128,
+}
+
+impl DynamicIntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn take(&mut self, start: usize) -> Option<usize> {
+        let stop = self.ranges.remove(&start)?;
+        self.coverage -= IngredientRange::new(start, stop).len();
+        Some(stop)
+    }
+
+    fn put(&mut self, start: usize, stop: usize) {
+        self.ranges.insert(start, stop);
+        self.coverage += IngredientRange::new(start, stop).len();
+    }
+
+    // The range that starts last at or before `id`, which is the only one
+    // that could hold it.
+    fn range_at(&self, id: usize) -> Option<(usize, usize)> {
+        self.ranges
+            .range(..=id)
+            .next_back()
+            .map(|(&start, &stop)| (start, stop))
+    }
+
+    pub fn insert(&mut self, range: IngredientRange) {
+        let mut start = range.start;
+        let mut stop = range.stop;
+
+        // Fold in a range that starts before this one and runs into it.
+        if let Some((s, e)) = self.range_at(start) {
+            if e >= start {
+                self.take(s);
+                start = s;
+                stop = stop.max(e);
+            }
+        }
+        // Then everything that starts inside it.
+        while let Some((s, e)) = self.range_at(stop) {
+            if s < start {
+                break;
+            }
+            self.take(s);
+            stop = stop.max(e);
+        }
+
+        self.put(start, stop);
+    }
+
+    pub fn remove(&mut self, range: IngredientRange) {
+        // Every range that starts at or before the end of the removal and
+        // hasn't finished before its start loses a piece, working backwards.
+        while let Some((s, e)) = self.range_at(range.stop) {
+            if e < range.start {
                 break;
             }
+            self.take(s);
+            if s < range.start {
+                self.put(s, range.start - 1);
+            }
+            if e > range.stop {
+                self.put(range.stop + 1, e);
+            }
+            if s < range.start {
+                break;
+            }
+        }
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.range_at(id).is_some_and(|(_, stop)| id <= stop)
+    }
+
+    pub fn coverage(&self) -> u128 {
+        self.coverage
+    }
+
+    pub fn to_set(&self) -> IntervalSet {
+        IntervalSet {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|(&start, &stop)| IngredientRange::new(start, stop))
+                .collect(),
+        }
+    }
+}
+
+// Every range behind every verdict. Ranges are referred to by their position
+// in the input list, so they can be traced back to the line they came from.
+#[derive(Debug)]
+struct FreshnessReport {
+    // Each ingredient in input order, with the ranges covering it (none
+    // means it has spoiled).
+    verdicts: Vec<(usize, Vec<usize>)>,
+    // Ranges that don't cover a single ingredient.
+    unused: Vec<usize>,
+}
+
+impl FreshnessReport {
+    // Sweep the ingredients in order, keeping a heap of the ranges that have
+    // started, and dropping each range once the sweep passes its stop.
+    pub fn new((ranges, ingredients): &(Vec<IngredientRange>, Vec<usize>)) -> Self {
+        let mut by_start: Vec<usize> = (0..ranges.len()).collect();
+        by_start.sort_by_key(|&r| ranges[r].start);
+        let mut order: Vec<usize> = (0..ingredients.len()).collect();
+        order.sort_by_key(|&i| ingredients[i]);
+
+        let mut verdicts: Vec<(usize, Vec<usize>)> =
+            ingredients.iter().map(|&id| (id, Vec::new())).collect();
+        let mut used = vec![false; ranges.len()];
+        let mut active: BinaryHeap<Reverse<(usize, usize)>> = BinaryHeap::new();
+        let mut next = 0;
+        for i in order {
+            let id = ingredients[i];
+            while next < by_start.len() && ranges[by_start[next]].start <= id {
+                let r = by_start[next];
+                active.push(Reverse((ranges[r].stop, r)));
+                next += 1;
+            }
+            while active.peek().is_some_and(|Reverse((stop, _))| *stop < id) {
+                active.pop();
+            }
+
+            let mut covering: Vec<usize> = active.iter().map(|Reverse((_, r))| *r).collect();
+            covering.sort_unstable();
+            for &r in &covering {
+                used[r] = true;
+            }
+            verdicts[i].1 = covering;
         }
+
+        let unused = (0..ranges.len()).filter(|&r| !used[r]).collect();
+        FreshnessReport { verdicts, unused }
     }
 
+    pub fn num_fresh(&self) -> usize {
+        self.verdicts.iter().filter(|(_, r)| !r.is_empty()).count()
+    }
+
+    // One row per ingredient. Range numbers are 1-based to match the line
+    // numbers in the input.
+    pub fn to_csv(&self, ranges: &[IngredientRange]) -> String {
+        let mut csv = String::from("ingredient,verdict,range_lines,ranges\n");
+        for (id, covering) in &self.verdicts {
+            let verdict = if covering.is_empty() {
+                "spoiled"
+            } else {
+                "fresh"
+            };
+            let lines: Vec<String> = covering.iter().map(|r| (r + 1).to_string()).collect();
+            let spans: Vec<String> = covering
+                .iter()
+                .map(|&r| format!("{}-{}", ranges[r].start, ranges[r].stop))
+                .collect();
+            csv.push_str(&format!(
+                "{id},{verdict},{},{}\n",
+                lines.join(" "),
+                spans.join(" ")
+            ));
+        }
+        csv
+    }
+
+    pub fn unused_csv(&self, ranges: &[IngredientRange]) -> String {
+        let mut csv = String::from("range_line,range\n");
+        for &r in &self.unused {
+            csv.push_str(&format!(
+                "{},{}-{}\n",
+                r + 1,
+                ranges[r].start,
+                ranges[r].stop
+            ));
+        }
+        csv
+    }
+}
+
+fn report_fresh(input: &(Vec<IngredientRange>, Vec<usize>)) -> FreshnessReport {
+    let report = FreshnessReport::new(input);
+
+    print!("{}", report.to_csv(&input.0));
+    print!("{}", report.unused_csv(&input.0));
+    println!("fresh: {}", report.num_fresh());
+
+    report
+}
+
+fn compute_fresh((ranges, ingredients): &(Vec<IngredientRange>, Vec<usize>)) -> usize {
+    let index = IntervalSet::new(ranges);
+    let fresh = ingredients.iter().filter(|i| index.contains(**i)).count();
+
+    println!("fresh: {fresh}");
+
+    fresh
+}
+
+fn compute_fresh_batched((ranges, ingredients): &(Vec<IngredientRange>, Vec<usize>)) -> usize {
+    let index = IntervalSet::new(ranges);
+    let mut sorted = ingredients.clone();
+    sorted.sort_unstable();
+    let fresh = index.count_sorted(&sorted);
+
     println!("fresh: {fresh}");
 
     fresh
 }
 
+// Compare the fresh lists from two suppliers.
+fn compare_suppliers(first: &str, second: &str) -> u128 {
+    let a = IntervalSet::new(&IngredientRange::parse_list(&get_input(first)).0);
+    let b = IntervalSet::new(&IngredientRange::parse_list(&get_input(second)).0);
+
+    let shared = a.intersection(&b);
+    println!(
+        "{first}: {} ids, {second}: {} ids",
+        a.coverage(),
+        b.coverage()
+    );
+    println!("shared: {}", shared.coverage());
+    println!("only {first}: {}", a.difference(&b).coverage());
+    println!("only {second}: {}", b.difference(&a).coverage());
+    println!(
+        "either but not both: {}",
+        a.symmetric_difference(&b).coverage()
+    );
+    println!(
+        "{first} within {second}: {}, any overlap: {}",
+        b.is_superset(&a),
+        a.overlaps(&b)
+    );
+    if let Some(last) = b.ranges.last() {
+        println!(
+            "{second} misses {} ids up to {}",
+            b.complement(0, last.stop).coverage(),
+            last.stop
+        );
+    }
+    println!(
+        "{second} has {} gaps, {} ranges ({} once touching ones are joined)",
+        b.gaps().count(),
+        b.ranges.len(),
+        IntervalSet::coalesced(&b.ranges).ranges.len()
+    );
+
+    shared.coverage()
+}
+
+// Build up the fresh ranges one at a time, then pull the first few back out,
+// checking the ingredients as we go.
+fn update_ranges((ranges, ingredients): &(Vec<IngredientRange>, Vec<usize>)) -> usize {
+    let mut set = DynamicIntervalSet::new();
+    for range in ranges {
+        set.insert(*range);
+    }
+    let fresh = ingredients.iter().filter(|i| set.contains(**i)).count();
+    println!("fresh: {fresh}, covering {}", set.coverage());
+
+    for range in ranges.iter().take(10) {
+        set.remove(*range);
+    }
+    let fresh = ingredients.iter().filter(|i| set.contains(**i)).count();
+    println!(
+        "fresh after removals: {fresh}, covering {} in {} ranges",
+        set.coverage(),
+        set.to_set().ranges.len()
+    );
+
+    fresh
+}
+
 #[test]
 fn test_prelim() {
     let fresh = compute_fresh(&IngredientRange::parse_list(&get_input("prelim.txt")));
@@ -123,9 +554,276 @@ fn test_part2() {
     assert_eq!(sum, 357907198933892);
 }
 
+#[test]
+fn test_fresh_index() {
+    let input = IngredientRange::parse_list(&get_input("input.txt"));
+    assert_eq!(compute_fresh_batched(&input), 862);
+    assert_eq!(
+        compute_fresh_batched(&IngredientRange::parse_list(&get_input("prelim.txt"))),
+        3
+    );
+
+    let index = IntervalSet::new(&input.0);
+    assert!(index.ranges.windows(2).all(|w| w[0].stop < w[1].start));
+    for ingredient in &input.1 {
+        let expected = input
+            .0
+            .iter()
+            .any(|r| r.start <= *ingredient && *ingredient <= r.stop);
+        assert_eq!(index.contains(*ingredient), expected);
+    }
+
+    let index = IntervalSet::new(&IngredientRange::parse_list(&get_input("prelim.txt")).0);
+    let checks = [
+        (0, false),
+        (3, true),
+        (5, true),
+        (6, false),
+        (10, true),
+        (20, true),
+        (21, false),
+    ];
+    for (ingredient, fresh) in checks {
+        assert_eq!(index.contains(ingredient), fresh, "{ingredient}");
+    }
+    let sorted: Vec<usize> = (0..25).collect();
+    assert_eq!(index.count_sorted(&sorted), 3 + 11);
+}
+
+#[test]
+fn test_merge() {
+    let ranges = IngredientRange::parse_list(&get_input("prelim.txt")).0;
+    let merged = IngredientRange::merge(&ranges);
+    assert_eq!(
+        merged,
+        vec![IngredientRange::new(3, 5), IngredientRange::new(10, 20)]
+    );
+    assert_eq!(IngredientRange::coverage(&merged), 14);
+
+    // Lots of nested and chained ranges, which used to mean a lot of
+    // Vec::remove calls.
+    let ranges: Vec<IngredientRange> = (0..100_000)
+        .map(|i| IngredientRange::new(i * 2, i * 2 + 3))
+        .chain((0..1000).map(|i| IngredientRange::new(i, i)))
+        .collect();
+    let merged = IngredientRange::merge(&ranges);
+    assert_eq!(merged, vec![IngredientRange::new(0, 200_001)]);
+}
+
+#[test]
+fn test_interval_set() {
+    let set = |ranges: &[(usize, usize)]| {
+        let ranges: Vec<IngredientRange> = ranges
+            .iter()
+            .map(|&(start, stop)| IngredientRange::new(start, stop))
+            .collect();
+        IntervalSet::new(&ranges)
+    };
+    let a = set(&[(3, 5), (10, 14), (16, 20), (12, 18)]);
+    let b = set(&[(0, 3), (8, 11), (19, 30)]);
+
+    assert_eq!(a, set(&[(3, 5), (10, 20)]));
+    assert_eq!(a.union(&b), set(&[(0, 5), (8, 30)]));
+    assert_eq!(a.intersection(&b), set(&[(3, 3), (10, 11), (19, 20)]));
+    assert_eq!(a.difference(&b), set(&[(4, 5), (12, 18)]));
+    assert_eq!(b.difference(&a), set(&[(0, 2), (8, 9), (21, 30)]));
+    assert_eq!(
+        a.symmetric_difference(&b),
+        set(&[(0, 2), (4, 5), (8, 9), (12, 18), (21, 30)])
+    );
+    assert_eq!(a.complement(0, 25), set(&[(0, 2), (6, 9), (21, 25)]));
+    assert_eq!(a.complement(4, 12), set(&[(6, 9)]));
+    assert_eq!(set(&[(0, usize::MAX)]).complement(0, usize::MAX), set(&[]));
+    assert_eq!(
+        a.gaps().collect::<Vec<_>>(),
+        vec![IngredientRange::new(6, 9)]
+    );
+    assert_eq!(a.coverage(), 14);
+
+    assert!(a.contains_range(&IngredientRange::new(11, 19)));
+    assert!(!a.contains_range(&IngredientRange::new(5, 10)));
+    assert!(a.is_superset(&set(&[(4, 4), (10, 20)])));
+    assert!(!a.is_superset(&b));
+    assert!(a.overlaps(&b));
+    assert!(!a.overlaps(&set(&[(6, 9), (21, 40)])));
+
+    // Check the set operations against plain membership.
+    let ranges = IngredientRange::parse_list(&get_input("input.txt")).0;
+    let (left, right) = ranges.split_at(ranges.len() / 2);
+    let (left, right) = (IntervalSet::new(left), IntervalSet::new(right));
+    let union = left.union(&right);
+    let inter = left.intersection(&right);
+    let diff = left.difference(&right);
+    let sym = left.symmetric_difference(&right);
+    let mut probes: Vec<usize> = Vec::new();
+    for r in &ranges {
+        probes.extend([
+            r.start - 1,
+            r.start,
+            r.start + 1,
+            r.stop - 1,
+            r.stop,
+            r.stop + 1,
+        ]);
+    }
+    for id in probes {
+        let (l, r) = (left.contains(id), right.contains(id));
+        assert_eq!(union.contains(id), l || r);
+        assert_eq!(inter.contains(id), l && r);
+        assert_eq!(diff.contains(id), l && !r);
+        assert_eq!(sym.contains(id), l != r);
+    }
+    assert_eq!(
+        union.coverage(),
+        left.coverage() + right.coverage() - inter.coverage()
+    );
+}
+
+#[test]
+fn test_dynamic_set() {
+    let mut set = DynamicIntervalSet::new();
+    for (start, stop) in [(3, 5), (10, 14), (16, 20), (12, 18)] {
+        set.insert(IngredientRange::new(start, stop));
+    }
+    assert_eq!(set.coverage(), 14);
+    assert!(set.contains(17) && !set.contains(7));
+
+    set.remove(IngredientRange::new(4, 11));
+    assert_eq!(
+        set.to_set().ranges,
+        vec![IngredientRange::new(3, 3), IngredientRange::new(12, 20)]
+    );
+    assert_eq!(set.coverage(), 10);
+    set.remove(IngredientRange::new(15, 15));
+    set.insert(IngredientRange::new(0, 12));
+    assert_eq!(
+        set.to_set().ranges,
+        vec![IngredientRange::new(0, 14), IngredientRange::new(16, 20)]
+    );
+    assert_eq!(set.coverage(), 20);
+
+    // A long run of random updates, checked against rebuilding a plain set
+    // of IDs each time.
+    let mut seed = 99u64;
+    let mut next = |limit: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % limit
+    };
+    let mut set = DynamicIntervalSet::new();
+    let mut expected = vec![false; 300];
+    for _ in 0..2000 {
+        let start = next(280);
+        let range = IngredientRange::new(start, start + next(20));
+        let adding = next(3) > 0;
+        if adding {
+            set.insert(range);
+        } else {
+            set.remove(range);
+        }
+        expected[range.start..=range.stop].fill(adding);
+
+        assert_eq!(
+            set.coverage(),
+            expected.iter().filter(|e| **e).count() as u128
+        );
+        for (id, e) in expected.iter().enumerate() {
+            assert_eq!(set.contains(id), *e);
+        }
+        let merged = set.to_set();
+        assert!(merged.ranges.windows(2).all(|w| w[0].stop < w[1].start));
+    }
+}
+
+#[test]
+fn test_full_domain() {
+    let max = usize::MAX;
+    let mut ranges = vec![
+        IngredientRange::new(max - 10, max),
+        IngredientRange::new(0, 5),
+        IngredientRange::new(max - 3, max),
+    ];
+    assert_eq!(IngredientRange::merge_ranges(&mut ranges), 11 + 6);
+
+    let all = IngredientRange::new(0, max);
+    assert_eq!(all.len(), 1 << 64);
+    let mut ranges = vec![all, IngredientRange::new(max, max), all];
+    assert_eq!(IngredientRange::merge_ranges(&mut ranges), 1 << 64);
+
+    let mut set = DynamicIntervalSet::new();
+    set.insert(IngredientRange::new(10, max));
+    set.insert(IngredientRange::new(0, 20));
+    assert_eq!(set.coverage(), 1 << 64);
+    set.remove(IngredientRange::new(max, max));
+    set.remove(IngredientRange::new(0, 0));
+    assert_eq!(set.coverage(), (1 << 64) - 2);
+    assert!(!set.contains(max) && set.contains(max - 1));
+
+    // Touching ranges only come together when asked to.
+    let ranges = vec![
+        IngredientRange::new(5, 7),
+        IngredientRange::new(8, 9),
+        IngredientRange::new(0, 3),
+        IngredientRange::new(max - 1, max),
+        IngredientRange::new(10, max - 2),
+    ];
+    assert_eq!(IngredientRange::merge(&ranges).len(), 5);
+    assert_eq!(
+        IngredientRange::merge_with(&ranges, true),
+        vec![IngredientRange::new(0, 3), IngredientRange::new(5, max)]
+    );
+    let set = IntervalSet::coalesced(&ranges);
+    assert_eq!(set.coverage(), IntervalSet::new(&ranges).coverage());
+    assert_eq!(
+        set.gaps().collect::<Vec<_>>(),
+        vec![IngredientRange::new(4, 4)]
+    );
+    assert_eq!(set.complement(0, max).coverage(), 1);
+}
+
+#[test]
+fn test_report() {
+    let input = IngredientRange::parse_list(&get_input("prelim.txt"));
+    let report = report_fresh(&input);
+    assert_eq!(
+        report.verdicts,
+        vec![
+            (1, vec![]),
+            (5, vec![0]),
+            (8, vec![]),
+            (11, vec![1]),
+            (17, vec![2, 3]),
+            (32, vec![]),
+        ]
+    );
+    assert!(report.unused.is_empty());
+    let csv = report.to_csv(&input.0);
+    assert_eq!(csv.lines().nth(1), Some("1,spoiled,,"));
+    assert_eq!(csv.lines().nth(5), Some("17,fresh,3 4,16-20 12-18"));
+
+    let input = IngredientRange::parse_list(&get_input("input.txt"));
+    let report = FreshnessReport::new(&input);
+    assert_eq!(report.num_fresh(), 862);
+    for (id, covering) in &report.verdicts {
+        let expected: Vec<usize> = (0..input.0.len())
+            .filter(|&r| input.0[r].start <= *id && *id <= input.0[r].stop)
+            .collect();
+        assert_eq!(*covering, expected);
+    }
+    assert_eq!(
+        report.unused_csv(&input.0).lines().count(),
+        report.unused.len() + 1
+    );
+}
+
 fn main() {
     compute_fresh(&IngredientRange::parse_list(&get_input("prelim.txt")));
+    report_fresh(&IngredientRange::parse_list(&get_input("prelim.txt")));
     compute_fresh(&IngredientRange::parse_list(&get_input("input.txt")));
+    compute_fresh_batched(&IngredientRange::parse_list(&get_input("input.txt")));
+    compare_suppliers("prelim.txt", "input.txt");
+    update_ranges(&IngredientRange::parse_list(&get_input("input.txt")));
     IngredientRange::merge_ranges(&mut IngredientRange::parse_list(&get_input("prelim.txt")).0);
     IngredientRange::merge_ranges(&mut IngredientRange::parse_list(&get_input("input.txt")).0);
 }
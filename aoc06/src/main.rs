@@ -1,6 +1,8 @@
+use std::fmt;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
+use std::rc::Rc;
 
 fn get_input(filename: &str) -> Vec<String> {
     let file = File::open(filename).unwrap();
@@ -13,65 +15,246 @@ fn get_input(filename: &str) -> Vec<String> {
     lines
 }
 
+// A binary operator a worksheet column can use. Problems are folded left to
+// right, ((a op b) op c) and so on, starting from the first operand. The
+// identity is what a problem with no operands at all comes out as. For -, /
+// and ^ that's their right identity (a - 0, a / 1 and a ^ 1 are all a); %
+// has none, so an empty problem using it is an error.
+#[derive(Clone)]
+struct Operator {
+    symbol: String,
+    identity: Option<i128>,
+    apply: Rc<dyn Fn(i128, i128) -> i128>,
+}
+
+impl fmt::Debug for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Operator")
+            .field("symbol", &self.symbol)
+            .field("identity", &self.identity)
+            .finish()
+    }
+}
+
+impl Operator {
+    pub fn fold(&self, operands: &[i128]) -> i128 {
+        let Some((first, rest)) = operands.split_first() else {
+            return self
+                .identity
+                .unwrap_or_else(|| panic!("No operands for {}", self.symbol));
+        };
+        rest.iter().fold(*first, |acc, x| (self.apply)(acc, *x))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct OperatorRegistry {
+    ops: Vec<Operator>,
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        let mut registry = OperatorRegistry { ops: Vec::new() };
+        registry.register("+", Some(0), |a, b| a.checked_add(b).unwrap());
+        registry.register("*", Some(1), |a, b| a.checked_mul(b).unwrap());
+        registry.register("-", Some(0), |a, b| a.checked_sub(b).unwrap());
+        registry.register("/", Some(1), |a, b| a.checked_div(b).unwrap());
+        registry.register("%", None, |a, b| a.checked_rem(b).unwrap());
+        registry.register("min", Some(i128::MAX), |a, b| a.min(b));
+        registry.register("max", Some(i128::MIN), |a, b| a.max(b));
+        registry.register("^", Some(1), |a, b| {
+            a.checked_pow(b.try_into().unwrap()).unwrap()
+        });
+        registry
+    }
+}
+
+impl OperatorRegistry {
+    // Add an operator, or replace one with the same symbol.
+    pub fn register(
+        &mut self,
+        symbol: &str,
+        identity: Option<i128>,
+        apply: impl Fn(i128, i128) -> i128 + 'static,
+    ) {
+        let op = Operator {
+            symbol: symbol.to_string(),
+            identity,
+            apply: Rc::new(apply),
+        };
+        match self.ops.iter_mut().find(|o| o.symbol == symbol) {
+            Some(existing) => *existing = op,
+            None => self.ops.push(op),
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> &Operator {
+        self.ops
+            .iter()
+            .find(|o| o.symbol == symbol)
+            .unwrap_or_else(|| panic!("Unhandled operation {symbol}"))
+    }
+}
+
+// Pads the lines out to the longest one, since editors like to strip
+// trailing spaces, and drops any blank lines after the operators.
+fn pad_rows(lines: &[String]) -> (Vec<Vec<char>>, usize) {
+    let mut lines = lines;
+    while let Some((last, rest)) = lines.split_last() {
+        if !last.trim().is_empty() {
+            break;
+        }
+        lines = rest;
+    }
+
+    let mut rows = lines
+        .iter()
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let line_len = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in rows.iter_mut() {
+        row.resize(line_len, ' ');
+    }
+    (rows, line_len)
+}
+
+// Column spans (inclusive) of each problem, left to right. Problems are
+// separated by columns that are blank in every row, however many there are.
+fn column_blocks(rows: &[Vec<char>], line_len: usize) -> Vec<(usize, usize)> {
+    let is_gutter = |col: usize| rows.iter().all(|row| row[col].is_whitespace());
+    let mut blocks = Vec::new();
+    let mut start = None;
+    for col in 0..line_len {
+        match (start, is_gutter(col)) {
+            (None, false) => start = Some(col),
+            (Some(s), true) => {
+                blocks.push((s, col - 1));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        blocks.push((s, line_len - 1));
+    }
+    blocks
+}
+
+// The operator can sit anywhere under its block and can be more than one
+// character, like "min". None if there isn't exactly one.
+fn block_operator(op_row: &[char], (start, end): (usize, usize)) -> Option<String> {
+    let op_text: String = op_row[start..=end].iter().collect();
+    let mut tokens = op_text.split_whitespace();
+    let (Some(op), None) = (tokens.next(), tokens.next()) else {
+        return None;
+    };
+    Some(op.to_string())
+}
+
+// Whitespace separated tokens in a row, along with the column each starts at.
+fn tokens(row: &[char]) -> Vec<(usize, String)> {
+    let mut tokens: Vec<(usize, String)> = Vec::new();
+    for (col, &ch) in row.iter().enumerate() {
+        if ch.is_whitespace() {
+            continue;
+        }
+        match tokens.last_mut() {
+            Some((start, token)) if *start + token.len() == col => token.push(ch),
+            _ => tokens.push((col, ch.to_string())),
+        }
+    }
+    tokens
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Problem {
+    operands: Vec<i128>,
+    op: String,
+}
+
+// How a single problem was read: where it sits on the sheet, its operands
+// in the order they were read, and what it came out as.
+#[derive(Clone, Debug, PartialEq)]
+struct ProblemResult {
+    span: (usize, usize),
+    problem: Problem,
+    value: i128,
+}
+
+impl ProblemResult {
+    pub fn render(&self) -> String {
+        let expr = self
+            .problem
+            .operands
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(&format!(" {} ", self.problem.op));
+        format!("{expr} = {}", self.value)
+    }
+}
+
 struct Worksheet {
-    problems: Vec<Vec<usize>>,
-    ops: Vec<char>,
+    problems: Vec<ProblemResult>,
 }
 
 impl Worksheet {
-    pub fn new(lines: &Vec<String>) -> Self {
-        let mut problems = Vec::new();
-        let mut ops = Vec::new();
+    pub fn new(lines: &[String]) -> Self {
+        Self::with_registry(lines, &OperatorRegistry::default())
+    }
 
-        for line in &lines[0..lines.len() - 1] {
-            let mut operand_line = Vec::new();
-            line.split_ascii_whitespace()
-                .collect::<Vec<_>>()
-                .iter()
-                .for_each(|&n| {
-                    operand_line.push(n.parse::<usize>().unwrap());
-                });
-            problems.push(operand_line);
+    pub fn with_registry(lines: &[String], registry: &OperatorRegistry) -> Self {
+        let (rows, _) = pad_rows(lines);
+        let (op_row, operand_rows) = rows.split_last().unwrap();
+
+        // Every token remembers the column it starts at, so each problem's
+        // span can be worked out from wherever its numbers ended up.
+        let ops = tokens(op_row);
+        let mut operand_lines = Vec::new();
+        for (row, line) in operand_rows.iter().enumerate() {
+            let operand_line = tokens(line);
+            if operand_line.len() != ops.len() {
+                panic!(
+                    "Row {row} has {} numbers for {} operators: {}",
+                    operand_line.len(),
+                    ops.len(),
+                    lines[row]
+                );
+            }
+            operand_lines.push(operand_line);
         }
 
-        lines[lines.len() - 1]
-            .split_ascii_whitespace()
-            .collect::<Vec<_>>()
-            .iter()
-            .for_each(|&s| match s {
-                "*" | "+" => ops.push(s.chars().nth(0).unwrap()),
-                c => panic!("Unhandled operation {c}"),
+        let mut problems = Vec::new();
+        for (i, (op_col, op)) in ops.into_iter().enumerate() {
+            let mut span = (op_col, op_col + op.len() - 1);
+            let mut operands = Vec::new();
+            for operand_line in &operand_lines {
+                let (col, n) = &operand_line[i];
+                span = (span.0.min(*col), span.1.max(col + n.len() - 1));
+                operands.push(n.parse::<i128>().unwrap());
+            }
+            let value = registry.get(&op).fold(&operands);
+            problems.push(ProblemResult {
+                span,
+                problem: Problem { operands, op },
+                value,
             });
+        }
 
         //println!("problems {problems:?}");
-        //println!("ops {ops:?}");
-        Worksheet { problems, ops }
+        Worksheet { problems }
     }
 
-    pub fn calculate(&self) -> usize {
-        let mut total_sum = 0;
+    pub fn breakdown(&self) -> &[ProblemResult] {
+        &self.problems
+    }
 
-        for (i, op) in self.ops.iter().enumerate() {
-            match op {
-                '*' => {
-                    let mut product = 1;
-                    for operand in &self.problems {
-                        product *= operand[i];
-                    }
-                    total_sum += product;
-                }
-                '+' => {
-                    let mut sum = 0;
-                    for operand in &self.problems {
-                        sum += operand[i];
-                    }
-                    total_sum += sum;
-                }
-                c => {
-                    panic!("Unhandled operation {c}");
-                }
-            }
-        }
+    pub fn problems(&self) -> Vec<Problem> {
+        self.problems.iter().map(|p| p.problem.clone()).collect()
+    }
+
+    pub fn calculate(&self) -> i128 {
+        let total_sum = self.problems.iter().map(|p| p.value).sum();
 
         println!("total_sum: {total_sum}");
         total_sum
@@ -81,28 +264,29 @@ impl Worksheet {
 struct CephalopodWorksheet {
     rows: Vec<Vec<char>>,
     line_len: usize,
+    registry: OperatorRegistry,
 }
 
 impl CephalopodWorksheet {
     pub fn new(lines: &[String]) -> Self {
-        let mut rows = Vec::new();
+        Self::with_registry(lines, &OperatorRegistry::default())
+    }
 
-        let line_len = lines[0].len();
+    pub fn with_registry(lines: &[String], registry: &OperatorRegistry) -> Self {
+        let (rows, line_len) = pad_rows(lines);
 
-        for line in lines {
-            let row_chars = line.chars().collect::<Vec<_>>();
-            if row_chars.len() != line_len {
-                panic!("Mismatched line lengths!\n{line}");
-            }
-            rows.push(row_chars);
+        Self {
+            rows,
+            line_len,
+            registry: registry.clone(),
         }
-
-        Self { rows, line_len }
     }
 
-    fn calc_ceph(&self, cur_index: usize, last_index: usize) -> usize {
-        let mut result;
+    fn blocks(&self) -> Vec<(usize, usize)> {
+        column_blocks(&self.rows, self.line_len)
+    }
 
+    fn calc_ceph(&self, cur_index: usize, last_index: usize) -> Option<ProblemResult> {
         let mut operands = Vec::new();
         // Get the operands first
         for col in (cur_index..=last_index).rev() {
@@ -110,62 +294,187 @@ impl CephalopodWorksheet {
             let mut operand = 0;
             for row in 0..self.rows.len() - 1 {
                 let ch = self.rows[row][col];
-                if ch.is_digit(10) {
+                if ch.is_ascii_digit() {
                     found = true;
                     operand *= 10;
-                    operand += ch.to_digit(10).unwrap();
+                    operand += ch.to_digit(10).unwrap() as i128;
                 }
             }
             if found {
-                operands.push(operand as usize);
+                operands.push(operand);
             }
         }
 
         let op_row = &self.rows[self.rows.len() - 1];
-        let op = op_row[cur_index];
-        match op {
-            '*' => {
-                result = 1;
-                for operand in operands {
-                    result *= operand;
-                }
-            },
-            '+' => {
-                result = 0;
-                for operand in operands {
-                    result += operand;
-                }
-            },
-            c => { panic!("Invalid char: {c}"); },
-        }
+        let op = block_operator(op_row, (cur_index, last_index))?;
+        let value = self.registry.get(&op).fold(&operands);
+        Some(ProblemResult {
+            span: (cur_index, last_index),
+            problem: Problem { operands, op },
+            value,
+        })
+    }
 
-        result
+    // Problems in cephalopod reading order, right to left
+    pub fn breakdown(&self) -> Vec<ProblemResult> {
+        self.blocks()
+            .iter()
+            .rev()
+            .map(|&(cur_index, last_index)| {
+                self.calc_ceph(cur_index, last_index).unwrap_or_else(|| {
+                    panic!("Expected one operator in columns {cur_index}..={last_index}")
+                })
+            })
+            .collect()
     }
 
-    pub fn calculate(&self) -> usize {
-        let mut total_sum = 0;
+    // Problems in sheet order, left to right, like Worksheet::problems
+    pub fn problems(&self) -> Vec<Problem> {
+        self.breakdown()
+            .into_iter()
+            .rev()
+            .map(|p| p.problem)
+            .collect()
+    }
 
-        let mut last_index = self.line_len - 1;
+    pub fn calculate(&self) -> i128 {
+        let total_sum = self.breakdown().iter().map(|p| p.value).sum();
 
-        let op_row = &self.rows[self.rows.len() - 1];
+        println!("total_sum (cephalopod): {total_sum}");
+        total_sum
+    }
+}
+
+// Lays the problems out left to right the way humans write them: operands
+// right-aligned one per row, the operator left-aligned underneath, and a
+// single blank column between problems.
+fn render_rows(problems: &[Problem]) -> Vec<String> {
+    // Rows are read a number at a time, so there's no leaving a gap
+    let height = problems.first().map_or(0, |p| p.operands.len());
+    if problems.iter().any(|p| p.operands.len() != height) {
+        panic!("Every problem in a row worksheet needs {height} operands");
+    }
+    let mut lines = vec![String::new(); height + 1];
 
-        while last_index != 0 {
-            let mut cur_index = last_index;
-            while op_row[cur_index] == ' ' {
-                cur_index -= 1;
+    for (i, problem) in problems.iter().enumerate() {
+        let operands = problem
+            .operands
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        let width = operands
+            .iter()
+            .map(|s| s.len())
+            .chain([problem.op.len()])
+            .max()
+            .unwrap();
+
+        for (row, line) in lines[..height].iter_mut().enumerate() {
+            if i > 0 {
+                line.push(' ');
             }
-            total_sum += self.calc_ceph(cur_index, last_index);
+            line.push_str(&format!("{:>width$}", operands[row]));
+        }
+        if i > 0 {
+            lines[height].push(' ');
+        }
+        lines[height].push_str(&format!("{:<width$}", problem.op));
+    }
 
-            last_index = cur_index;
-            if last_index != 0 {
-                // hop over the empty column too
-                last_index -= 2;
+    lines
+        .iter()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+// Lays the problems out for cephalopods: each operand becomes a column of
+// digits read top to bottom, and the operands of a problem run from right to
+// left.
+fn render_cephalopod(problems: &[Problem]) -> Vec<String> {
+    let digits = |n: &i128| {
+        if *n < 0 {
+            panic!("Cephalopods can't write negative numbers: {n}");
+        }
+        n.to_string().chars().collect::<Vec<_>>()
+    };
+    let height = problems
+        .iter()
+        .flat_map(|p| p.operands.iter().map(|n| digits(n).len()))
+        .max()
+        .unwrap_or(0);
+    let mut rows = vec![Vec::new(); height + 1];
+
+    for (i, problem) in problems.iter().enumerate() {
+        let width = problem.operands.len().max(problem.op.len());
+        let start = rows[height].len() + usize::from(i > 0);
+        for row in rows.iter_mut() {
+            row.resize(start + width, ' ');
+        }
+
+        for (k, operand) in problem.operands.iter().enumerate() {
+            let col = start + width - 1 - k;
+            for (row, digit) in digits(operand).into_iter().enumerate() {
+                rows[row][col] = digit;
             }
         }
+        for (k, ch) in problem.op.chars().enumerate() {
+            rows[height][start + k] = ch;
+        }
+    }
 
-        println!("total_sum (cephalopod): {total_sum}");
-        total_sum
+    rows.iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+// Both readings of every problem next to each other, one line per problem
+// from left to right, so it's easy to check how each column was taken. The
+// two readings only line up on sheets where the problems sit in their own
+// columns; anything only one of them found is marked as unmatched.
+fn breakdown_report(lines: &[String]) -> Vec<String> {
+    let rows = Worksheet::new(lines);
+    // Blocks the cephalopods can't make sense of are reported rather than
+    // given up on.
+    let ceph_sheet = CephalopodWorksheet::new(lines);
+    let ceph = ceph_sheet
+        .blocks()
+        .into_iter()
+        .map(|(start, end)| ((start, end), ceph_sheet.calc_ceph(start, end)))
+        .collect::<Vec<_>>();
+
+    let mut spans: Vec<(usize, usize)> = rows
+        .breakdown()
+        .iter()
+        .map(|p| p.span)
+        .chain(ceph.iter().map(|c| c.0))
+        .collect();
+    spans.sort();
+    spans.dedup();
+
+    let rendered = spans
+        .into_iter()
+        .map(|span| {
+            let row_text = rows
+                .breakdown()
+                .iter()
+                .find(|p| p.span == span)
+                .map_or("(unmatched)".to_string(), |p| p.render());
+            let ceph_text = match ceph.iter().find(|c| c.0 == span) {
+                Some((_, Some(p))) => p.render(),
+                Some((_, None)) => "(no single operator)".to_string(),
+                None => "(unmatched)".to_string(),
+            };
+            (span, row_text, ceph_text)
+        })
+        .collect::<Vec<_>>();
+    let width = rendered.iter().map(|r| r.1.len()).max().unwrap_or(0);
+
+    let mut report = vec![format!("{:<9} {:<width$} | cephalopod", "columns", "rows")];
+    for ((start, end), row_text, ceph_text) in rendered {
+        let span = format!("{start}-{end}");
+        report.push(format!("{span:<9} {row_text:<width$} | {ceph_text}"));
     }
+    report
 }
 
 #[test]
@@ -192,6 +501,202 @@ fn test_part2() {
     assert_eq!(sum, 11494432585168);
 }
 
+#[test]
+fn test_operators() {
+    let registry = OperatorRegistry::default();
+    let fold = |symbol: &str, operands: &[i128]| registry.get(symbol).fold(operands);
+    assert_eq!(fold("-", &[20, 5, 3]), 12);
+    assert_eq!(fold("/", &[100, 5, 2]), 10);
+    assert_eq!(fold("%", &[100, 30, 7]), 3);
+    assert_eq!(fold("^", &[2, 3, 2]), 64);
+    assert_eq!(fold("min", &[4, -2, 9]), -2);
+    assert_eq!(fold("max", &[4, -2, 9]), 9);
+    assert_eq!(fold("+", &[]), 0);
+    assert_eq!(fold("*", &[]), 1);
+    assert_eq!(fold("min", &[]), i128::MAX);
+    assert_eq!(fold("-", &[]), 0);
+    assert_eq!(fold("/", &[]), 1);
+    assert_eq!(fold("^", &[]), 1);
+
+    let lines: Vec<String> = ["10 7  3", " 4 2 12", "-  ^  min"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(Worksheet::new(&lines).calculate(), 6 + 49 + 3);
+
+    // A custom operator, and replacing a standard one with a closure.
+    let mut registry = OperatorRegistry::default();
+    registry.register("avg", None, |a, b| (a + b) / 2);
+    let weight = 2;
+    registry.register("+", Some(0), move |a, b| a + weight * b);
+    let lines: Vec<String> = ["10  7", " 4  3", "avg +"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        Worksheet::with_registry(&lines, &registry).calculate(),
+        7 + 13
+    );
+
+    // The cephalopod layout, read right to left a column at a time:
+    // 24 - 13 and max(15, 42, 32).
+    let lines: Vec<String> = ["12 341", "34 225", "-  max"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(CephalopodWorksheet::new(&lines).calculate(), 11 + 42);
+}
+
+#[test]
+#[should_panic(expected = "No operands for %")]
+fn test_empty_remainder() {
+    OperatorRegistry::default().get("%").fold(&[]);
+}
+
+#[test]
+fn test_breakdown() {
+    let lines = get_input("prelim.txt");
+    let worksheet = Worksheet::new(&lines);
+    let first = &worksheet.breakdown()[0];
+    assert_eq!(first.span, (0, 2));
+    assert_eq!(first.problem.operands, vec![123, 45, 6]);
+    assert_eq!(first.problem.op, "*");
+    assert_eq!(first.value, 33210);
+
+    // Cephalopods start with the rightmost problem
+    let ceph = CephalopodWorksheet::new(&lines).breakdown();
+    assert_eq!(ceph[0].span, (12, 14));
+    assert_eq!(ceph[0].problem.operands, vec![4, 431, 623]);
+    assert_eq!(ceph[0].value, 1058);
+    assert_eq!(ceph[3].problem.operands, vec![356, 24, 1]);
+
+    let report = breakdown_report(&lines);
+    assert_eq!(report.len(), 5);
+    assert_eq!(
+        report[1],
+        "0-2       123 * 45 * 6 = 33210     | 356 * 24 * 1 = 8544"
+    );
+}
+
+#[test]
+fn test_render() {
+    let lines = get_input("prelim.txt");
+    let problems = Worksheet::new(&lines).problems();
+    let rows = render_rows(&problems);
+    assert_eq!(rows[0], "123 328  51  64");
+    assert_eq!(rows[3], "*   +   *   +");
+    assert_eq!(Worksheet::new(&rows).problems(), problems);
+
+    let ceph_problems = CephalopodWorksheet::new(&lines).problems();
+    let ceph = render_cephalopod(&ceph_problems);
+    assert_eq!(CephalopodWorksheet::new(&ceph).problems(), ceph_problems);
+
+    // Converting between the two conventions keeps the problems intact
+    let converted = render_cephalopod(&problems);
+    assert_eq!(CephalopodWorksheet::new(&converted).problems(), problems);
+    assert_eq!(
+        CephalopodWorksheet::new(&converted).calculate(),
+        Worksheet::new(&lines).calculate()
+    );
+    let converted = render_rows(&ceph_problems);
+    assert_eq!(Worksheet::new(&converted).problems(), ceph_problems);
+
+    // Wide operators
+    let mut problems = vec![
+        Problem {
+            operands: vec![7, 1000],
+            op: "max".to_string(),
+        },
+        Problem {
+            operands: vec![5, 4],
+            op: "-".to_string(),
+        },
+        Problem {
+            operands: vec![2, 3],
+            op: "^".to_string(),
+        },
+    ];
+    let rows = render_rows(&problems);
+    assert_eq!(Worksheet::new(&rows).problems(), problems);
+    let ceph = render_cephalopod(&problems);
+    assert_eq!(CephalopodWorksheet::new(&ceph).problems(), problems);
+
+    // Cephalopods can also cope with uneven operand counts
+    problems[0].operands.push(12);
+    problems[1].operands.pop();
+    let ceph = render_cephalopod(&problems);
+    assert_eq!(CephalopodWorksheet::new(&ceph).problems(), problems);
+}
+
+#[test]
+fn test_unaligned_rows() {
+    // Numbers jotted down without lining them up still read row by row.
+    let lines: Vec<String> = ["123 45", "6 789", "+ *"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let worksheet = Worksheet::new(&lines);
+    let problems = worksheet.breakdown();
+    assert_eq!(problems[0].span, (0, 2));
+    assert_eq!(problems[0].problem.operands, vec![123, 6]);
+    assert_eq!(problems[1].span, (2, 5));
+    assert_eq!(problems[1].problem.operands, vec![45, 789]);
+    assert_eq!(worksheet.calculate(), 129 + 45 * 789);
+
+    // Neither problem sits in its own columns, so the cephalopod reading
+    // doesn't line up with either of them.
+    let report = breakdown_report(&lines);
+    assert_eq!(report.len(), 4);
+    assert!(report[1].starts_with("0-2       123 + 6 = 129"));
+    assert!(report[1].ends_with("| (unmatched)"));
+    assert!(report[2].starts_with("0-5       (unmatched)"));
+    assert!(report[2].ends_with("| (no single operator)"));
+    assert!(report[3].starts_with("2-5       45 * 789 = 35505"));
+}
+
+#[test]
+#[should_panic(expected = "Row 1 has 1 numbers for 2 operators")]
+fn test_unaligned_missing_number() {
+    let lines: Vec<String> = ["123 45", "6789", "+ *"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    Worksheet::new(&lines);
+}
+
+#[test]
+fn test_segmentation() {
+    let expected = CephalopodWorksheet::new(&get_input("prelim.txt")).calculate();
+
+    // Rebuild it with uneven gutters, trailing spaces stripped, operators
+    // moved around under their blocks and a stray blank line at the end;
+    // none of it should change the answer.
+    let mut lines: Vec<String> = get_input("prelim.txt")[..3]
+        .iter()
+        .map(|line| {
+            let line = format!(
+                "{}   {} {}    {}",
+                &line[0..3],
+                &line[4..7],
+                &line[8..11],
+                &line[12..15]
+            );
+            line.trim_end().to_string()
+        })
+        .collect();
+    lines.push(format!(
+        "  *{}+{}*{}+",
+        " ".repeat(4),
+        " ".repeat(2),
+        " ".repeat(8)
+    ));
+    lines.push(String::new());
+
+    let worksheet = CephalopodWorksheet::new(&lines);
+    assert_eq!(worksheet.blocks(), vec![(0, 2), (6, 8), (10, 12), (17, 19)]);
+    assert_eq!(worksheet.calculate(), expected);
+}
+
 fn main() {
     let worksheet = Worksheet::new(&get_input("prelim.txt"));
     worksheet.calculate();
@@ -201,4 +706,17 @@ fn main() {
     worksheet.calculate();
     let worksheet = CephalopodWorksheet::new(&get_input("input.txt"));
     worksheet.calculate();
+    for line in breakdown_report(&get_input("prelim.txt")) {
+        println!("{line}");
+    }
+
+    // The prelim problems, written the other way round
+    let problems = Worksheet::new(&get_input("prelim.txt")).problems();
+    for line in render_cephalopod(&problems) {
+        println!("{line}");
+    }
+    let problems = CephalopodWorksheet::new(&get_input("prelim.txt")).problems();
+    for line in render_rows(&problems) {
+        println!("{line}");
+    }
 }
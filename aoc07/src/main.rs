@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
@@ -14,6 +14,73 @@ fn get_input(filename: &str) -> Vec<String> {
     lines
 }
 
+// Timeline counts double with every splitter a beam hits, so a tall enough
+// manifold outgrows any fixed width integer. Only addition is needed, so
+// this is just a little-endian list of u64 limbs.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Timelines {
+    limbs: Vec<u64>,
+}
+
+impl Timelines {
+    pub fn one() -> Self {
+        Timelines { limbs: vec![1] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.iter().all(|&limb| limb == 0)
+    }
+
+    pub fn add(&mut self, other: &Timelines) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        let mut carry = false;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let rhs = other.limbs.get(i).copied().unwrap_or(0);
+            let (sum, c1) = limb.overflowing_add(rhs);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+            if !carry && i >= other.limbs.len() {
+                break;
+            }
+        }
+        if carry {
+            self.limbs.push(1);
+        }
+    }
+}
+
+impl fmt::Display for Timelines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Peel off 18 decimal digits at a time, least significant first
+        const CHUNK: u128 = 1_000_000_000_000_000_000;
+        let mut limbs = self.limbs.clone();
+        let mut chunks = Vec::new();
+        while limbs.iter().any(|&limb| limb != 0) {
+            let mut rem: u128 = 0;
+            for limb in limbs.iter_mut().rev() {
+                let cur = (rem << 64) | *limb as u128;
+                *limb = (cur / CHUNK) as u64;
+                rem = cur % CHUNK;
+            }
+            chunks.push(rem);
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{first}")?;
+                for chunk in rest.iter().rev() {
+                    write!(f, "{chunk:018}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 struct TachyonGrid {
     rows: usize,
     cols: usize,
@@ -33,7 +100,7 @@ impl TachyonGrid {
         self.elems[self.get_idx(col, row)]
     }
 
-    pub fn create(lines: &Vec<String>) -> Self {
+    pub fn create(lines: &[String]) -> Self {
         let rows = lines.len();
         let cols = lines[0].len();
         let mut elems: Box<[char]> = vec![' '; rows * cols].into_boxed_slice();
@@ -132,42 +199,36 @@ impl TachyonGrid {
         splits
     }
 
-    // Dynamic programming memoization (recursive helper function)
-    fn count_timelines_memo(
-        &self,
-        memo: &mut HashMap<(usize, usize), usize>,
-        col: usize,
-        row: usize,
-    ) -> usize {
-        if memo.contains_key(&(col, row)) {
-            return *memo.get(&(col, row)).unwrap();
-        }
-
-        // Base case for recursion
-        if row == self.rows - 1 {
-            memo.insert((col, row), 1);
-            return 1;
-        }
+    // Dynamic programming, one row at a time from the start down. Each
+    // entry holds how many timelines put a beam in that column, so only a
+    // single row of counts is ever kept around.
+    pub fn count_timelines(&self) -> Timelines {
+        let (col, row) = self.get_start();
+        let mut counts = vec![Timelines::default(); self.cols];
+        counts[col] = Timelines::one();
 
-        let timelines = match self.get_elem(col, row) {
-            '.' | 'S' | '|' => self.count_timelines_memo(memo, col, row + 1),
-            '^' => {
-                self.count_timelines_memo(memo, col - 1, row + 1)
-                    + self.count_timelines_memo(memo, col + 1, row + 1)
-            }
-            c => {
-                panic!("Unhandled tachyon space {c}");
+        for row in row..self.rows - 1 {
+            let mut next = vec![Timelines::default(); self.cols];
+            for (col, count) in counts.iter().enumerate() {
+                if count.is_zero() {
+                    continue;
+                }
+                match self.get_elem(col, row) {
+                    '.' | 'S' | '|' => next[col].add(count),
+                    '^' => {
+                        next[col - 1].add(count);
+                        next[col + 1].add(count);
+                    }
+                    c => {
+                        panic!("Unhandled tachyon space {c}");
+                    }
+                }
             }
-        };
-        memo.insert((col, row), timelines);
-        timelines
-    }
-
-    pub fn count_timelines(&self) -> usize {
-        let mut memo = HashMap::new();
-        let (col, row) = self.get_start();
+            counts = next;
+        }
 
-        let timelines = self.count_timelines_memo(&mut memo, col, row);
+        let mut timelines = Timelines::default();
+        counts.iter().for_each(|count| timelines.add(count));
         println!("timelines: {timelines}");
         timelines
     }
@@ -191,14 +252,58 @@ fn test_part1() {
 fn test_prelim2() {
     let grid = TachyonGrid::create(&get_input("prelim.txt"));
     let timelines = grid.count_timelines();
-    assert_eq!(timelines, 40);
+    assert_eq!(timelines.to_string(), "40");
 }
 
 #[test]
 fn test_part2() {
     let grid = TachyonGrid::create(&get_input("input.txt"));
     let timelines = grid.count_timelines();
-    assert_eq!(timelines, 5137133207830);
+    assert_eq!(timelines.to_string(), "5137133207830");
+}
+
+// A manifold where every beam hits a splitter on every row, so the number
+// of timelines doubles each time.
+#[cfg(test)]
+fn doubling_grid(splitter_rows: usize) -> TachyonGrid {
+    let cols = 2 * splitter_rows + 3;
+    let start = splitter_rows + 1;
+    let mut lines = Vec::new();
+    let mut top = vec!['.'; cols];
+    top[start] = 'S';
+    lines.push(top.iter().collect::<String>());
+    for row in 0..splitter_rows {
+        let line = (0..cols)
+            .map(|col| {
+                if (col + row + start).is_multiple_of(2) {
+                    '^'
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        lines.push(line);
+    }
+    lines.push(".".repeat(cols));
+    TachyonGrid::create(&lines)
+}
+
+#[test]
+fn test_deep_manifold() {
+    let timelines = doubling_grid(100).count_timelines();
+    assert_eq!(timelines.to_string(), (1u128 << 100).to_string());
+
+    let timelines = doubling_grid(200).count_timelines();
+    assert_eq!(timelines.limbs.len(), 4);
+    assert_eq!(
+        timelines.to_string(),
+        "1606938044258990275541962092341162602522202993782792835301376"
+    );
+
+    // Tall enough that recursing once per row would blow the stack
+    let timelines = doubling_grid(2000).count_timelines();
+    assert_eq!(timelines.limbs.len(), 2000 / 64 + 1);
+    assert_eq!(timelines.limbs.last(), Some(&(1 << (2000 % 64))));
 }
 
 fn main() {